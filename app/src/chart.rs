@@ -0,0 +1,218 @@
+use crate::models::common::{Candle, Drawing, DrawingKind, Operation, OperationType, TradeView};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use plotters::coord::Shift;
+use plotters::coord::types::RangedCoordf32;
+use plotters::prelude::*;
+use std::path::Path;
+
+const LEVEL_COLOR: RGBColor = RGBColor(218, 165, 32);
+
+/// Indicator line over the candles, one value per candle
+pub struct ChartLine {
+    pub values: Vec<Option<f32>>,
+    /// rgb
+    pub color: (u8, u8, u8),
+}
+
+/// Everything drawn besides the candles, all of it optional
+#[derive(Default)]
+pub struct ChartLayers<'a> {
+    /// trades of every candle, drawn as buy/sell bars under the candles
+    pub trades: &'a [TradeView],
+    pub lines: Vec<ChartLine>,
+    /// operations of the security
+    pub operations: &'a [Operation],
+    pub drawings: &'a [Drawing],
+}
+
+pub fn save_png(
+    path: &Path,
+    title: &str,
+    candles: &[Candle],
+    layers: &ChartLayers,
+    size: (u32, u32),
+) -> Result<()> {
+    let root = BitMapBackend::new(path, size).into_drawing_area();
+    draw_chart(&root, title, candles, layers)?;
+    root.present()?;
+    Ok(())
+}
+
+pub fn save_svg(
+    path: &Path,
+    title: &str,
+    candles: &[Candle],
+    layers: &ChartLayers,
+    size: (u32, u32),
+) -> Result<()> {
+    let root = SVGBackend::new(path, size).into_drawing_area();
+    draw_chart(&root, title, candles, layers)?;
+    root.present()?;
+    Ok(())
+}
+
+/// Candles scaled to their prices with the layers, the trades take
+/// the bottom 30% of the area
+fn draw_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    title: &str,
+    candles: &[Candle],
+    layers: &ChartLayers,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        anyhow::bail!("no candles to draw");
+    };
+    let period = first.end - first.begin;
+    let (from, to) = (first.begin - period, last.end + period);
+
+    let low = candles.iter().map(|a| a.low).fold(f32::MAX, f32::min);
+    let high = candles.iter().map(|a| a.high).fold(f32::MIN, f32::max);
+    let margin = ((high - low) * 0.05).max(0.01);
+
+    let (width, height) = root.dim_in_pixel();
+    let (upper, lower) = if layers.trades.is_empty() {
+        (root.clone(), None)
+    } else {
+        let (upper, lower) = root.split_vertically(height * 7 / 10);
+        (upper, Some(lower))
+    };
+
+    let mut chart = ChartBuilder::on(&upper)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .caption(title, ("sans-serif", 24.0).into_font())
+        .build_cartesian_2d(
+            RangedDateTime::from(from..to),
+            (low - margin)..(high + margin),
+        )?;
+    chart.configure_mesh().light_line_style(WHITE).draw()?;
+
+    let candle_w = (width as f32 * 0.7 / candles.len() as f32).clamp(1.0, 20.0) as u32;
+    chart.draw_series(candles.iter().map(|a| {
+        CandleStick::new(
+            a.begin,
+            a.open,
+            a.high,
+            a.low,
+            a.close,
+            GREEN.filled(),
+            RED.filled(),
+            candle_w,
+        )
+    }))?;
+
+    for line in layers.lines.iter() {
+        let points = candles
+            .iter()
+            .zip(&line.values)
+            .filter_map(|(candle, value)| value.map(|a| (candle.begin, a)));
+        let (r, g, b) = line.color;
+        chart.draw_series(LineSeries::new(points, RGBColor(r, g, b)))?;
+    }
+
+    for operation in layers.operations {
+        let point = (operation.time_at, operation.price);
+        let color = match operation.operation_type {
+            OperationType::Buy | OperationType::Short => GREEN,
+            OperationType::Sold | OperationType::Cover => RED,
+        };
+        match operation.operation_type {
+            OperationType::Buy | OperationType::Cover => {
+                chart.draw_series([TriangleMarker::new(point, 6, color.filled())])?;
+            }
+            OperationType::Short | OperationType::Sold => {
+                chart.draw_series([Cross::new(point, 5, color.stroke_width(2))])?;
+            }
+        }
+    }
+
+    for drawing in layers.drawings {
+        draw_drawing(&mut chart, drawing, from, to)?;
+    }
+
+    if let Some(lower) = lower {
+        draw_trades(&lower, candles, layers.trades, from, to)?;
+    }
+    Ok(())
+}
+
+fn draw_drawing<DB: DrawingBackend>(
+    chart: &mut ChartContext<DB, Cartesian2d<RangedDateTime<NaiveDateTime>, RangedCoordf32>>,
+    drawing: &Drawing,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let begin = (drawing.begin, drawing.begin_price);
+    let end = (drawing.end, drawing.end_price);
+    match drawing.kind {
+        DrawingKind::Level => {
+            let points = [(from, drawing.begin_price), (to, drawing.begin_price)];
+            chart.draw_series(LineSeries::new(points, LEVEL_COLOR))?;
+        }
+        DrawingKind::Line => {
+            chart.draw_series(LineSeries::new([begin, end], LEVEL_COLOR.stroke_width(2)))?;
+        }
+        DrawingKind::Rect => {
+            chart.draw_series([
+                Rectangle::new([begin, end], LEVEL_COLOR.mix(0.15).filled()),
+                Rectangle::new([begin, end], LEVEL_COLOR),
+            ])?;
+        }
+        DrawingKind::Note => {
+            let style = ("sans-serif", 14.0).into_font().color(&BLACK);
+            chart.draw_series([Text::new(drawing.text.clone(), begin, style)])?;
+        }
+    }
+    Ok(())
+}
+
+/// Buy quantities up and sell quantities down of every candle
+fn draw_trades<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    candles: &[Candle],
+    trades: &[TradeView],
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let max = trades
+        .iter()
+        .map(|a| a.quantity_buy.max(a.quantity_sell))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+    let mut chart = ChartBuilder::on(area)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(RangedDateTime::from(from..to), -max..max)?;
+    chart.configure_mesh().light_line_style(WHITE).draw()?;
+
+    chart.draw_series(candles.iter().zip(trades).flat_map(|(candle, trade)| {
+        [
+            Rectangle::new(
+                [(candle.begin, 0.0), (candle.end, trade.quantity_buy as f32)],
+                GREEN.mix(0.6).filled(),
+            ),
+            Rectangle::new(
+                [
+                    (candle.begin, 0.0),
+                    (candle.end, -(trade.quantity_sell as f32)),
+                ],
+                RED.mix(0.6).filled(),
+            ),
+        ]
+    }))?;
+    Ok(())
+}
@@ -1,10 +1,10 @@
 use crate::models::common::{
-    Attempt, AvgPeriod, Candle, Frame, Operation, SecuritiesStr, StartInfo, ToSql, Trade,
-    TradeInfo, TradeView,
+    Attempt, AvgPeriod, Candle, Drawing, DrawingKind, Fold, Frame, Operation, OperationType,
+    SecuritiesStr, StartInfo, Tick, ToSql, Trade, TradeInfo, TradeView,
 };
 use chrono::NaiveDateTime;
 use dotenv;
-use sqlx::postgres::PgPool;
+use sqlx::postgres::{PgConnection, PgPool};
 use sqlx::types::Uuid;
 
 pub async fn init_db() -> PgPool {
@@ -240,6 +240,33 @@ pub async fn get_all_securities(pool: &PgPool) -> Vec<String> {
         .collect::<Vec<String>>();
 }
 
+/// Recorded trades in time order, used to simulate fills
+pub async fn get_ticks(
+    pool: &PgPool,
+    security: &str,
+    begin: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Vec<Tick> {
+    let sql = r#"
+    select
+        t.trade_datetime as time, t.price, t.quantity, t.buysell
+    from public.trades as t
+    inner join public.securities as s on s.id = t.security_id
+    where s.code = $1
+        and t.trade_datetime >= $2
+        and t.trade_datetime <= $3
+    order by t.trade_datetime, t.trade_no;
+        "#;
+
+    sqlx::query_as::<_, Tick>(sql)
+        .bind(security)
+        .bind(begin)
+        .bind(end)
+        .fetch_all(pool)
+        .await
+        .expect("failed to fetch ticks")
+}
+
 pub async fn get_candles(
     pool: &PgPool,
     security: &str,
@@ -359,22 +386,115 @@ limit $4;
     result
 }
 
-pub async fn add_attempt(pool: &PgPool, attempt: &Attempt) {
+/// Runs on a connection to be a part of the transaction of the result
+pub async fn add_attempt(conn: &mut PgConnection, attempt: &Attempt) {
     let sql = r#"
-    insert into public.attempts(id, created_at, profit, commission)
-    values($1, now(), $2, $3);
+    insert into public.attempts(
+        id, created_at, strategy, securities, begin_t, end_t, frame,
+        version, profit, commission, borrow_fee, fill, params, metrics)
+    values($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13::jsonb, $14::jsonb);
         "#;
 
     let _ = sqlx::query(sql)
         .bind(attempt.id)
+        .bind(attempt.created_at)
+        .bind(&attempt.strategy)
+        .bind(&attempt.securities)
+        .bind(attempt.begin)
+        .bind(attempt.end)
+        .bind(&attempt.frame)
+        .bind(&attempt.version)
         .bind(attempt.profit)
         .bind(attempt.commission)
-        .execute(pool)
+        .bind(attempt.borrow_fee)
+        .bind(&attempt.fill)
+        .bind(serde_json::to_string(&attempt.params).expect("failed to serialize params"))
+        .bind(attempt.metrics.to_string())
+        .execute(conn)
         .await
         .unwrap();
 }
 
-pub async fn add_operation(pool: &PgPool, operation: &Operation, prev_uuid: Option<Uuid>) {
+pub async fn get_attempts(pool: &PgPool, limit: i32) -> Vec<Attempt> {
+    let sql = r#"
+    select
+        a.id, a.created_at, a.strategy, a.securities, a.begin_t, a.end_t, a.frame,
+        a.version, a.profit::float4, a.commission::float4, a.borrow_fee::float4,
+        a.fill, a.params::text, a.metrics::text
+    from public.attempts as a
+    order by a.created_at desc
+    limit $1;
+        "#;
+
+    let rows = sqlx::query_as::<_, AttemptRow>(sql)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .expect("failed to fetch attempts");
+
+    rows.into_iter().map(to_attempt).collect()
+}
+
+pub async fn get_attempt(pool: &PgPool, id: Uuid) -> Option<Attempt> {
+    let sql = r#"
+    select
+        a.id, a.created_at, a.strategy, a.securities, a.begin_t, a.end_t, a.frame,
+        a.version, a.profit::float4, a.commission::float4, a.borrow_fee::float4,
+        a.fill, a.params::text, a.metrics::text
+    from public.attempts as a
+    where a.id = $1;
+        "#;
+
+    let row = sqlx::query_as::<_, AttemptRow>(sql)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .expect("failed to fetch attempt");
+
+    row.map(to_attempt)
+}
+
+type AttemptRow = (
+    Uuid,
+    NaiveDateTime,
+    String,
+    Vec<String>,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
+    String,
+    String,
+    f32,
+    f32,
+    f32,
+    String,
+    String,
+    String,
+);
+
+fn to_attempt(row: AttemptRow) -> Attempt {
+    Attempt {
+        id: row.0,
+        created_at: row.1,
+        strategy: row.2,
+        securities: row.3,
+        begin: row.4,
+        end: row.5,
+        frame: row.6,
+        version: row.7,
+        profit: row.8,
+        commission: row.9,
+        borrow_fee: row.10,
+        fill: row.11,
+        params: serde_json::from_str(&row.12).unwrap_or_default(),
+        metrics: serde_json::from_str(&row.13).unwrap_or_default(),
+    }
+}
+
+pub async fn add_operation(
+    conn: &mut PgConnection,
+    operation: &Operation,
+    prev_uuid: Option<Uuid>,
+) {
     let sql = r#"
     insert into public.operations(
         id, attempt_id, operation_type, security_id, count,
@@ -396,11 +516,185 @@ pub async fn add_operation(pool: &PgPool, operation: &Operation, prev_uuid: Opti
         .bind(operation.sum_before)
         .bind(operation.sum_after)
         .bind(prev_uuid)
-        .execute(pool)
+        .execute(conn)
         .await
         .unwrap();
 }
 
+pub async fn add_fold(pool: &PgPool, fold: &Fold) {
+    let sql = r#"
+    insert into public.folds(
+        id, run_id, attempt_id, fold, security_id, in_begin, in_end,
+        out_begin, out_end, in_return, out_return, out_drawdown, out_sharpe)
+    select $1, $2, $3, $4, s.id, $6, $7, $8, $9, $10, $11, $12, $13
+    from public.securities as s
+    where s.code = $5;
+        "#;
+
+    let _ = sqlx::query(sql)
+        .bind(fold.id)
+        .bind(fold.run)
+        .bind(fold.attempt)
+        .bind(fold.fold)
+        .bind(&fold.security)
+        .bind(fold.in_begin)
+        .bind(fold.in_end)
+        .bind(fold.out_begin)
+        .bind(fold.out_end)
+        .bind(fold.in_return)
+        .bind(fold.out_return)
+        .bind(fold.out_drawdown)
+        .bind(fold.out_sharpe)
+        .execute(pool)
+        .await
+        .expect("failed to insert fold");
+}
+
+pub async fn get_operations(pool: &PgPool, attempt: Uuid) -> Vec<Operation> {
+    // операции связаны в цепочку через prev
+    let sql = r#"
+    with recursive chain as
+    (
+        select o.*, 0 as n
+        from public.operations as o
+        where o.attempt_id = $1
+            and o.prev is null
+        union all
+        select o.*, c.n + 1
+        from public.operations as o
+        inner join chain as c on c.id = o.prev
+    )
+    select
+        o.id, o.attempt_id, o.operation_type, s.code, o.count,
+        o.price::float4, o.commission::float4, o.time_at,
+        o.sum_before::float4, o.sum_after::float4
+    from chain as o
+    inner join public.securities as s on s.id = o.security_id
+    order by o.n;
+        "#;
+
+    let rows = sqlx::query_as::<
+        _,
+        (
+            Uuid,
+            Uuid,
+            String,
+            String,
+            i32,
+            f32,
+            f32,
+            NaiveDateTime,
+            f32,
+            f32,
+        ),
+    >(sql)
+    .bind(attempt)
+    .fetch_all(pool)
+    .await
+    .expect("failed to fetch operations");
+
+    rows.into_iter()
+        .map(|r| Operation {
+            id: r.0,
+            attempt: r.1,
+            operation_type: OperationType::from(r.2.as_str()),
+            security: r.3,
+            count: r.4,
+            price: r.5,
+            commission: r.6,
+            time_at: r.7,
+            sum_before: r.8,
+            sum_after: r.9,
+        })
+        .collect()
+}
+
+pub async fn get_drawings(pool: &PgPool, security: &str) -> Vec<Drawing> {
+    let sql = r#"
+    select
+        d.id, s.code, d.kind, d.begin_t, d.begin_price::float4,
+        d.end_t, d.end_price::float4, d.text
+    from public.drawings as d
+    inner join public.securities as s on s.id = d.security_id
+    where s.code = $1
+    order by d.created_at;
+        "#;
+
+    let rows = sqlx::query_as::<
+        _,
+        (
+            Uuid,
+            String,
+            String,
+            NaiveDateTime,
+            f32,
+            NaiveDateTime,
+            f32,
+            String,
+        ),
+    >(sql)
+    .bind(security)
+    .fetch_all(pool)
+    .await
+    .expect("failed to fetch drawings");
+
+    rows.into_iter()
+        .map(|r| Drawing {
+            id: r.0,
+            security: r.1,
+            kind: DrawingKind::from(r.2.as_str()),
+            begin: r.3,
+            begin_price: r.4,
+            end: r.5,
+            end_price: r.6,
+            text: r.7,
+        })
+        .collect()
+}
+
+/// Inserts the drawing or updates it after it was moved or its text changed
+pub async fn save_drawing(pool: &PgPool, drawing: &Drawing) {
+    let sql = r#"
+    insert into public.drawings(
+        id, security_id, kind, begin_t, begin_price, end_t, end_price, text)
+    select $1, s.id, $3, $4, $5, $6, $7, $8
+    from public.securities as s
+    where s.code = $2
+    on conflict (id) do update
+    set begin_t = excluded.begin_t,
+        begin_price = excluded.begin_price,
+        end_t = excluded.end_t,
+        end_price = excluded.end_price,
+        text = excluded.text;
+        "#;
+
+    let _ = sqlx::query(sql)
+        .bind(drawing.id)
+        .bind(&drawing.security)
+        .bind(drawing.kind.to_string())
+        .bind(drawing.begin)
+        .bind(drawing.begin_price)
+        .bind(drawing.end)
+        .bind(drawing.end_price)
+        .bind(&drawing.text)
+        .execute(pool)
+        .await
+        .expect("failed to save drawing");
+}
+
+pub async fn remove_drawing(pool: &PgPool, id: Uuid) {
+    let sql = r#"
+    delete from public.drawings
+    where id = $1;
+        "#;
+
+    let _ = sqlx::query(sql)
+        .bind(id)
+        .execute(pool)
+        .await
+        .expect("failed to remove drawing");
+}
+
 pub async fn get_average_volume(
     pool: &PgPool,
     security: &str,
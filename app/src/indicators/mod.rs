@@ -0,0 +1,246 @@
+pub mod moving;
+pub mod oscillators;
+pub mod volatility;
+pub mod volume;
+
+use crate::models::common::Candle;
+
+pub use moving::{Ema, Sma};
+pub use oscillators::{Macd, MacdValue, Rsi, Stochastic, StochasticValue};
+pub use volatility::{Atr, Bands, Bollinger};
+pub use volume::{Obv, Vwap};
+
+/// Streaming indicator: keeps its own state and is updated candle by candle,
+/// `batch` runs it over the whole series
+pub trait Indicator {
+    type Output;
+
+    /// Value after the candle, `None` while warming up
+    fn update(&mut self, candle: &Candle) -> Option<Self::Output>;
+}
+
+/// Values for every candle of the series, `None` while warming up
+pub fn batch<I: Indicator>(mut indicator: I, candles: &[Candle]) -> Vec<Option<I::Output>> {
+    candles.iter().map(|a| indicator.update(a)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    /// Closes of the 10-day moving average example of StockCharts ChartSchool
+    const CLOSES: [f32; 30] = [
+        22.27, 22.19, 22.08, 22.17, 22.18, 22.13, 22.23, 22.43, 22.24, 22.29, 22.15, 22.39, 22.38,
+        22.61, 23.36, 24.05, 23.75, 23.83, 23.95, 23.63, 23.82, 23.87, 23.65, 23.19, 23.10, 23.33,
+        22.68, 23.10, 22.40, 22.17,
+    ];
+
+    /// Closes of the 14-day RSI example of StockCharts ChartSchool
+    const RSI_CLOSES: [f32; 33] = [
+        44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89, 46.03, 45.61,
+        46.28, 46.28, 46.00, 46.03, 46.41, 46.22, 45.64, 46.21, 46.25, 45.71, 46.45, 45.78, 45.35,
+        44.03, 44.18, 44.22, 44.57, 43.42, 42.66, 43.13,
+    ];
+
+    /// m1 candles opened at the previous close, the first 15 on one day and
+    /// the rest on the next. High, low and volume follow a fixed pattern,
+    /// the expected values below are computed from the same series
+    fn series(closes: &[f32]) -> Vec<Candle> {
+        let start = NaiveDate::from_ymd_opt(2025, 3, 3)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let open = closes[i.saturating_sub(1)];
+                let begin = if i < 15 {
+                    start + Duration::minutes(i as i64)
+                } else {
+                    start + Duration::days(1) + Duration::minutes(i as i64)
+                };
+                Candle {
+                    open,
+                    close: *close,
+                    high: open.max(*close) + 0.1 * (i % 3 + 1) as f32,
+                    low: open.min(*close) - 0.1 * ((i + 1) % 3 + 1) as f32,
+                    value: 0.0,
+                    volume: (1000 + 100 * (i % 5)) as f32,
+                    begin,
+                    end: begin + Duration::seconds(59),
+                    position_x: None,
+                    position_y: None,
+                }
+            })
+            .collect()
+    }
+
+    /// `warmup` values are `None`, the rest match `expected`
+    fn check(values: &[Option<f32>], warmup: usize, expected: &[f32], tolerance: f32) {
+        assert_eq!(values.len(), warmup + expected.len());
+        assert!(values[..warmup].iter().all(|a| a.is_none()));
+        for (i, (value, expected)) in values[warmup..].iter().zip(expected).enumerate() {
+            let value = value.unwrap_or_else(|| panic!("no value at {}", warmup + i));
+            assert!(
+                (value - expected).abs() < tolerance,
+                "at {}: {} != {}",
+                warmup + i,
+                value,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn sma_of_reference_series() {
+        let values = batch(Sma::new(10), &series(&CLOSES));
+        let expected = [
+            22.22, 22.21, 22.23, 22.26, 22.30, 22.42, 22.61, 22.77, 22.91, 23.08, 23.21, 23.38,
+            23.52, 23.65, 23.71, 23.68, 23.61, 23.51, 23.43, 23.28, 23.13,
+        ];
+        check(&values, 9, &expected, 0.01);
+    }
+
+    #[test]
+    fn sma_does_not_drift() {
+        // цены с копейками в случайном порядке, длинная история минутных свечей
+        let mut seed: u32 = 1;
+        let prices = (0..1_000_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                250.0 + (seed >> 16) as f32 % 1000.0 / 100.0
+            })
+            .collect::<Vec<f32>>();
+        let mut sma = Sma::new(10);
+        let last = prices
+            .iter()
+            .map(|a| sma.push(*a))
+            .last()
+            .flatten()
+            .unwrap();
+        let window = &prices[prices.len() - 10..];
+        let expected = window.iter().map(|a| *a as f64).sum::<f64>() / 10.0;
+        assert!(
+            (last as f64 - expected).abs() < 1e-4,
+            "{last} != {expected}"
+        );
+    }
+
+    #[test]
+    fn ema_of_reference_series() {
+        let values = batch(Ema::new(10), &series(&CLOSES));
+        let expected = [
+            22.22, 22.21, 22.24, 22.27, 22.33, 22.52, 22.80, 22.97, 23.13, 23.28, 23.34, 23.43,
+            23.51, 23.53, 23.47, 23.40, 23.39, 23.26, 23.23, 23.08, 22.92,
+        ];
+        check(&values, 9, &expected, 0.01);
+    }
+
+    #[test]
+    fn rsi_of_reference_series() {
+        let values = batch(Rsi::new(14), &series(&RSI_CLOSES));
+        let expected = [
+            70.46, 66.25, 66.48, 69.35, 66.29, 57.92, 62.88, 63.21, 56.01, 62.34, 54.67, 50.39,
+            40.02, 41.49, 41.90, 45.50, 37.32, 33.09, 37.79,
+        ];
+        check(&values, 14, &expected, 0.01);
+    }
+
+    #[test]
+    fn macd_of_reference_series() {
+        let values = batch(Macd::new(5, 10, 3), &series(&CLOSES));
+        let macd = values.iter().map(|a| a.map(|a| a.macd)).collect::<Vec<_>>();
+        let signal = values
+            .iter()
+            .map(|a| a.map(|a| a.signal))
+            .collect::<Vec<_>>();
+        // линия MACD есть с 10-й свечи, но значение отдается вместе с сигнальной
+        let expected = [
+            0.0415, 0.0487, 0.0845, 0.2126, 0.3741, 0.3941, 0.3932, 0.3871, 0.3118, 0.2806, 0.2542,
+            0.1910, 0.0753, -0.0060, -0.0152, -0.1177, -0.1029, -0.1946, -0.2677,
+        ];
+        check(&macd, 11, &expected, 0.001);
+        let expected = [
+            0.0366, 0.0426, 0.0636, 0.1381, 0.2561, 0.3251, 0.3591, 0.3731, 0.3424, 0.3115, 0.2829,
+            0.2369, 0.1561, 0.0750, 0.0299, -0.0439, -0.0734, -0.1340, -0.2009,
+        ];
+        check(&signal, 11, &expected, 0.001);
+        for value in values.iter().flatten() {
+            assert!((value.histogram - (value.macd - value.signal)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn stochastic_of_reference_series() {
+        let values = batch(Stochastic::new(5, 3), &series(&CLOSES));
+        let k = values.iter().map(|a| a.map(|a| a.k)).collect::<Vec<_>>();
+        let d = values.iter().map(|a| a.map(|a| a.d)).collect::<Vec<_>>();
+        let expected = [
+            58.06, 73.68, 43.02, 45.00, 34.09, 61.36, 60.23, 79.17, 83.43, 95.24, 76.96, 80.65,
+            82.76, 43.12, 53.26, 65.85, 39.02, 9.43, 16.39, 41.73, 6.71, 42.18, 19.61, 6.41,
+        ];
+        check(&k, 6, &expected, 0.01);
+        let expected = [
+            50.00, 57.89, 58.26, 53.90, 40.70, 46.82, 51.89, 66.92, 74.27, 85.94, 85.21, 84.28,
+            80.12, 68.84, 59.71, 54.08, 52.71, 38.10, 21.62, 22.52, 21.61, 30.21, 22.83, 22.73,
+        ];
+        check(&d, 6, &expected, 0.01);
+    }
+
+    #[test]
+    fn bollinger_of_reference_series() {
+        let values = batch(Bollinger::new(10, 2.0), &series(&CLOSES));
+        let upper = values
+            .iter()
+            .map(|a| a.map(|a| a.upper))
+            .collect::<Vec<_>>();
+        let lower = values
+            .iter()
+            .map(|a| a.map(|a| a.lower))
+            .collect::<Vec<_>>();
+        let expected = [
+            22.41, 22.39, 22.44, 22.46, 22.59, 23.10, 23.77, 24.07, 24.33, 24.55, 24.62, 24.63,
+            24.62, 24.44, 24.21, 24.27, 24.18, 24.29, 24.22, 24.20, 24.23,
+        ];
+        check(&upper, 9, &expected, 0.01);
+        let expected = [
+            22.04, 22.02, 22.02, 22.05, 22.02, 21.74, 21.45, 21.46, 21.48, 21.60, 21.80, 22.12,
+            22.43, 22.87, 23.21, 23.09, 23.04, 22.72, 22.64, 22.36, 22.04,
+        ];
+        check(&lower, 9, &expected, 0.01);
+    }
+
+    #[test]
+    fn atr_of_reference_series() {
+        let values = batch(Atr::new(5), &series(&CLOSES));
+        let expected = [
+            0.46, 0.46, 0.45, 0.50, 0.51, 0.48, 0.51, 0.54, 0.49, 0.54, 0.66, 0.73, 0.74, 0.69,
+            0.64, 0.67, 0.66, 0.59, 0.62, 0.67, 0.61, 0.64, 0.72, 0.72, 0.82, 0.78,
+        ];
+        check(&values, 4, &expected, 0.01);
+    }
+
+    #[test]
+    fn vwap_resets_every_day() {
+        let values = batch(Vwap::default(), &series(&CLOSES));
+        let expected = [
+            22.24, 22.21, 22.20, 22.17, 22.17, 22.17, 22.17, 22.19, 22.21, 22.22, 22.21, 22.23,
+            22.24, 22.26, 22.33, 23.79, 23.80, 23.83, 23.84, 23.81, 23.81, 23.81, 23.80, 23.75,
+            23.67, 23.64, 23.59, 23.53, 23.46, 23.37,
+        ];
+        check(&values, 0, &expected, 0.01);
+    }
+
+    #[test]
+    fn obv_of_reference_series() {
+        let values = batch(Obv::default(), &series(&CLOSES));
+        let expected = [
+            0.0, -1100.0, -2300.0, -1000.0, 400.0, -600.0, 500.0, 1700.0, 400.0, 1800.0, 800.0,
+            1900.0, 700.0, 2000.0, 3400.0, 4400.0, 3300.0, 4500.0, 5800.0, 4400.0, 5400.0, 6500.0,
+            5300.0, 4000.0, 2600.0, 3600.0, 2500.0, 3700.0, 2400.0, 1000.0,
+        ];
+        check(&values, 0, &expected, 0.01);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::indicators::Indicator;
+use crate::models::common::Candle;
+use std::collections::VecDeque;
+
+/// Simple moving average of the close
+pub struct Sma {
+    period: usize,
+    values: VecDeque<f32>,
+    /// f64, in f32 the running sum drifts over a long history
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        let period = period.max(1);
+        Self {
+            period,
+            values: VecDeque::with_capacity(period + 1),
+            sum: 0.0,
+        }
+    }
+
+    pub fn push(&mut self, value: f32) -> Option<f32> {
+        self.values.push_back(value);
+        self.sum += value as f64;
+        if self.values.len() > self.period {
+            self.sum -= self.values.pop_front().unwrap_or(0.0) as f64;
+        }
+        if self.values.len() < self.period {
+            return None;
+        }
+        Some((self.sum / self.period as f64) as f32)
+    }
+}
+
+impl Indicator for Sma {
+    type Output = f32;
+
+    fn update(&mut self, candle: &Candle) -> Option<f32> {
+        self.push(candle.close)
+    }
+}
+
+/// Exponential moving average of the close,
+/// seeded with the simple average of the first `period` values
+pub struct Ema {
+    alpha: f32,
+    seed: Sma,
+    value: Option<f32>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Self {
+            alpha: 2.0 / (period.max(1) as f32 + 1.0),
+            seed: Sma::new(period),
+            value: None,
+        }
+    }
+
+    pub fn push(&mut self, value: f32) -> Option<f32> {
+        self.value = match self.value {
+            Some(prev) => Some(prev + self.alpha * (value - prev)),
+            None => self.seed.push(value),
+        };
+        self.value
+    }
+}
+
+impl Indicator for Ema {
+    type Output = f32;
+
+    fn update(&mut self, candle: &Candle) -> Option<f32> {
+        self.push(candle.close)
+    }
+}
+
+/// Wilder smoothing used by RSI and ATR,
+/// seeded with the simple average of the first `period` values
+pub(crate) struct Wilder {
+    period: f32,
+    seed: Sma,
+    value: Option<f32>,
+}
+
+impl Wilder {
+    pub(crate) fn new(period: usize) -> Self {
+        Self {
+            period: period.max(1) as f32,
+            seed: Sma::new(period),
+            value: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: f32) -> Option<f32> {
+        self.value = match self.value {
+            Some(prev) => Some((prev * (self.period - 1.0) + value) / self.period),
+            None => self.seed.push(value),
+        };
+        self.value
+    }
+}
@@ -0,0 +1,137 @@
+use crate::indicators::Indicator;
+use crate::indicators::moving::{Ema, Sma, Wilder};
+use crate::models::common::Candle;
+use std::collections::VecDeque;
+
+/// Relative strength index with Wilder smoothing, 0..100
+pub struct Rsi {
+    gain: Wilder,
+    loss: Wilder,
+    prev: Option<f32>,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Self {
+            gain: Wilder::new(period),
+            loss: Wilder::new(period),
+            prev: None,
+        }
+    }
+}
+
+impl Indicator for Rsi {
+    type Output = f32;
+
+    fn update(&mut self, candle: &Candle) -> Option<f32> {
+        let prev = self.prev.replace(candle.close)?;
+        let change = candle.close - prev;
+        let gain = self.gain.push(f32::max(change, 0.0));
+        let loss = self.loss.push(f32::max(-change, 0.0));
+        match (gain, loss) {
+            (Some(_), Some(0.0)) => Some(100.0),
+            (Some(gain), Some(loss)) => Some(100.0 - 100.0 / (1.0 + gain / loss)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MacdValue {
+    pub macd: f32,
+    pub signal: f32,
+    pub histogram: f32,
+}
+
+/// Difference of fast and slow EMA of the close with its signal line
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        Self {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+        }
+    }
+}
+
+impl Default for Macd {
+    fn default() -> Self {
+        Self::new(12, 26, 9)
+    }
+}
+
+impl Indicator for Macd {
+    type Output = MacdValue;
+
+    fn update(&mut self, candle: &Candle) -> Option<MacdValue> {
+        let fast = self.fast.push(candle.close);
+        let slow = self.slow.push(candle.close);
+        let macd = fast? - slow?;
+        let signal = self.signal.push(macd)?;
+        Some(MacdValue {
+            macd,
+            signal,
+            histogram: macd - signal,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StochasticValue {
+    /// %K, 0..100
+    pub k: f32,
+    /// %D, simple average of %K
+    pub d: f32,
+}
+
+/// Position of the close in the high-low range of the last `period` candles
+pub struct Stochastic {
+    period: usize,
+    candles: VecDeque<(f32, f32)>,
+    d: Sma,
+}
+
+impl Stochastic {
+    pub fn new(period: usize, d_period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            candles: VecDeque::with_capacity(period + 1),
+            d: Sma::new(d_period),
+        }
+    }
+}
+
+impl Default for Stochastic {
+    fn default() -> Self {
+        Self::new(14, 3)
+    }
+}
+
+impl Indicator for Stochastic {
+    type Output = StochasticValue;
+
+    fn update(&mut self, candle: &Candle) -> Option<StochasticValue> {
+        self.candles.push_back((candle.high, candle.low));
+        if self.candles.len() > self.period {
+            self.candles.pop_front();
+        }
+        if self.candles.len() < self.period {
+            return None;
+        }
+        let high = self.candles.iter().map(|a| a.0).fold(f32::MIN, f32::max);
+        let low = self.candles.iter().map(|a| a.1).fold(f32::MAX, f32::min);
+        let k = if high > low {
+            (candle.close - low) / (high - low) * 100.0
+        } else {
+            50.0
+        };
+        let d = self.d.push(k)?;
+        Some(StochasticValue { k, d })
+    }
+}
@@ -0,0 +1,91 @@
+use crate::indicators::Indicator;
+use crate::indicators::moving::Wilder;
+use crate::models::common::Candle;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Bands {
+    pub upper: f32,
+    pub middle: f32,
+    pub lower: f32,
+}
+
+/// Simple average of the close plus and minus `width` standard deviations
+pub struct Bollinger {
+    period: usize,
+    width: f32,
+    values: VecDeque<f32>,
+}
+
+impl Bollinger {
+    pub fn new(period: usize, width: f32) -> Self {
+        Self {
+            period: period.max(1),
+            width,
+            values: VecDeque::with_capacity(period + 1),
+        }
+    }
+}
+
+impl Default for Bollinger {
+    fn default() -> Self {
+        Self::new(20, 2.0)
+    }
+}
+
+impl Indicator for Bollinger {
+    type Output = Bands;
+
+    fn update(&mut self, candle: &Candle) -> Option<Bands> {
+        self.values.push_back(candle.close);
+        if self.values.len() > self.period {
+            self.values.pop_front();
+        }
+        if self.values.len() < self.period {
+            return None;
+        }
+        let count = self.period as f32;
+        let middle = self.values.iter().sum::<f32>() / count;
+        let variance = self
+            .values
+            .iter()
+            .map(|a| (a - middle) * (a - middle))
+            .sum::<f32>()
+            / count;
+        let deviation = f32::sqrt(variance) * self.width;
+        Some(Bands {
+            upper: middle + deviation,
+            middle,
+            lower: middle - deviation,
+        })
+    }
+}
+
+/// Average true range with Wilder smoothing
+pub struct Atr {
+    range: Wilder,
+    prev_close: Option<f32>,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Self {
+            range: Wilder::new(period),
+            prev_close: None,
+        }
+    }
+}
+
+impl Indicator for Atr {
+    type Output = f32;
+
+    fn update(&mut self, candle: &Candle) -> Option<f32> {
+        let range = match self.prev_close.replace(candle.close) {
+            Some(prev) => (candle.high - candle.low)
+                .max((candle.high - prev).abs())
+                .max((candle.low - prev).abs()),
+            None => candle.high - candle.low,
+        };
+        self.range.push(range)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::indicators::Indicator;
+use crate::models::common::Candle;
+use chrono::NaiveDate;
+
+/// Volume weighted average of the typical price, reset every day
+#[derive(Default)]
+pub struct Vwap {
+    date: Option<NaiveDate>,
+    value: f32,
+    volume: f32,
+}
+
+impl Indicator for Vwap {
+    type Output = f32;
+
+    fn update(&mut self, candle: &Candle) -> Option<f32> {
+        let date = candle.begin.date();
+        if self.date != Some(date) {
+            self.date = Some(date);
+            self.value = 0.0;
+            self.volume = 0.0;
+        }
+        let typical = (candle.high + candle.low + candle.close) / 3.0;
+        self.value += typical * candle.volume;
+        self.volume += candle.volume;
+        if self.volume > 0.0 {
+            Some(self.value / self.volume)
+        } else {
+            Some(typical)
+        }
+    }
+}
+
+/// On-balance volume: volume added on up closes and subtracted on down closes
+#[derive(Default)]
+pub struct Obv {
+    prev_close: Option<f32>,
+    value: f32,
+}
+
+impl Indicator for Obv {
+    type Output = f32;
+
+    fn update(&mut self, candle: &Candle) -> Option<f32> {
+        if let Some(prev) = self.prev_close {
+            if candle.close > prev {
+                self.value += candle.volume;
+            } else if candle.close < prev {
+                self.value -= candle.volume;
+            }
+        }
+        self.prev_close = Some(candle.close);
+        Some(self.value)
+    }
+}
@@ -1,9 +1,12 @@
+pub mod chart;
 pub mod db;
+pub mod indicators;
 pub mod models;
-mod strategy;
+pub mod strategy;
 mod utils;
 
 use anyhow::{Context, Result};
+use chart::ChartLayers;
 use chrono::prelude::*;
 use chrono::{Duration, NaiveDate};
 use clap::Parser;
@@ -14,14 +17,21 @@ use db::pg::{
 };
 use dotenv;
 use log::info;
-use models::common::{Candle, Frame, Trade, TradeInfo, TradeType};
-use plotters::prelude::*;
+use models::common::{
+    Candle, ExitRules, FillModel, Frame, Sizing, Trade, TradeInfo, TradeType, Wallet,
+};
 use sqlx::postgres::PgPool;
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 use std::time::Duration as time_duration;
-use strategy::strategy::{best_choice, pretty_print_candle, pretty_print_info, trade_info};
+use strategy::backtest::START_BALANCE;
+use strategy::portfolio::run_portfolio;
+use strategy::strategy::{
+    Settings, best_choice, pretty_print_candle, pretty_print_info, run_strategy, trade_info,
+};
+use strategy::sweep::run_sweep;
+use strategy::walk_forward::run_walk_forward;
 use utils::logger;
 
 /// Money maker app
@@ -43,18 +53,191 @@ struct Args {
     /// Show candles
     #[arg(short, long)]
     display: bool,
+
+    /// Run backtest of the strategy by name
+    #[arg(long)]
+    strategy: Option<String>,
+
+    /// Ranges of strategy params to sweep, e.g. profit=0.2:1.0:0.1,break_volume=5000:12000:1000
+    #[arg(long)]
+    sweep: Option<String>,
+
+    /// Tune sweep params on rolling in-sample days and check them out-of-sample
+    #[arg(long, requires = "sweep")]
+    walk_forward: bool,
+
+    /// Trading days of the in-sample window
+    #[arg(long, default_value_t = 20)]
+    in_sample: usize,
+
+    /// Trading days of the out-of-sample window
+    #[arg(long, default_value_t = 5)]
+    out_sample: usize,
+
+    /// Begin date of backtest
+    #[arg(long, default_value = "2024-06-10")]
+    begin: NaiveDate,
+
+    /// End date of backtest
+    #[arg(long, default_value = "2024-06-11")]
+    end: NaiveDate,
+
+    /// Candles frame: m1, m15, h1, d1
+    #[arg(long, default_value = "m1", value_parser = ["m1", "m15", "h1", "d1"])]
+    frame: String,
+
+    /// Save backtest attempt and operations to DB
+    #[arg(long)]
+    save: bool,
+
+    /// Trade all securities together against one wallet
+    #[arg(long, conflicts_with = "sweep")]
+    portfolio: bool,
+
+    /// Position sizing of the portfolio: equal, fraction, volatility
+    #[arg(long, default_value = "equal", value_parser = ["equal", "fraction", "volatility"])]
+    sizing: String,
+
+    /// Percent of equity per position, fraction sizing
+    #[arg(long, default_value_t = 10.0)]
+    fraction: f32,
+
+    /// Percent of equity at risk per average candle range, volatility sizing
+    #[arg(long, default_value_t = 1.0)]
+    risk: f32,
+
+    /// Max concurrent positions of the portfolio
+    #[arg(long)]
+    max_positions: Option<usize>,
+
+    /// Stop loss in percent from the entry price
+    #[arg(long)]
+    stop_loss: Option<f32>,
+
+    /// Trailing stop in percent from the highest price since entry
+    #[arg(long)]
+    trailing_stop: Option<f32>,
+
+    /// Borrow fee of short positions in percent per day
+    #[arg(long, default_value_t = 0.0)]
+    borrow_fee: f32,
+
+    /// Fill model of orders: open, trades
+    #[arg(long, default_value = "open", value_parser = ["open", "trades"])]
+    fill: String,
+
+    /// Delay before the order reaches the market in milliseconds, trades fill
+    #[arg(long, default_value_t = 0)]
+    latency: i64,
+
+    /// Slippage in percent against the order, trades fill
+    #[arg(long, default_value_t = 0.0)]
+    slippage: f32,
+
+    /// Percent of every trade volume available to the order, trades fill
+    #[arg(long, default_value_t = 100.0)]
+    participation: f32,
+
+    /// Close position after this many minutes
+    #[arg(long)]
+    max_hold: Option<i64>,
+
+    /// Close position and stop entries at this time, e.g. 18:40
+    #[arg(long)]
+    session_end: Option<NaiveTime>,
+
+    /// Show report of the attempt by id
+    #[arg(long)]
+    report: Option<String>,
+
+    /// List latest backtest attempts
+    #[arg(long)]
+    list_attempts: bool,
+
+    /// Show params, metadata and metrics of the attempt by id
+    #[arg(long)]
+    show_attempt: Option<String>,
+
+    /// Print report as JSON
+    #[arg(long)]
+    json: bool,
 }
 
 pub async fn run() {
     logger::init().expect("failed to init logging");
+    let args = Args::parse();
     let pool = init_db().await;
 
-    let args = Args::parse();
+    let securities = get_securities(&pool, &args).await;
+
+    if let Some(name) = &args.strategy {
+        let settings = Settings {
+            begin: args.begin.and_time(NaiveTime::MIN),
+            end: args.end.and_time(NaiveTime::MIN),
+            frame: Frame::from(args.frame.as_str()),
+            save: args.save,
+            json: args.json,
+            exits: ExitRules {
+                stop_loss: args.stop_loss,
+                trailing_stop: args.trailing_stop,
+                max_hold: args.max_hold.map(Duration::minutes),
+                session_end: args.session_end,
+            },
+            borrow_fee: args.borrow_fee,
+            fill: match args.fill.as_str() {
+                "trades" => FillModel::Trades {
+                    latency: Duration::milliseconds(args.latency),
+                    slippage: args.slippage,
+                    participation: args.participation,
+                },
+                _ => FillModel::Open,
+            },
+        };
+        match &args.sweep {
+            Some(spec) if args.walk_forward => {
+                run_walk_forward(
+                    &pool,
+                    name,
+                    &securities,
+                    spec,
+                    args.in_sample,
+                    args.out_sample,
+                    &settings,
+                )
+                .await
+            }
+            Some(spec) => run_sweep(&pool, name, &securities, spec, &settings).await,
+            None if args.portfolio => {
+                let wallet = Wallet {
+                    balance: START_BALANCE,
+                    sizing: match args.sizing.as_str() {
+                        "fraction" => Sizing::FixedFraction(args.fraction),
+                        "volatility" => Sizing::Volatility(args.risk),
+                        _ => Sizing::EqualWeight,
+                    },
+                    max_positions: args.max_positions,
+                };
+                run_portfolio(&pool, name, &securities, wallet, &settings).await
+            }
+            None => run_strategy(&pool, name, &securities, &settings).await,
+        }
+        return;
+    }
 
-    // strategy::strategy::run_strategy(&pool).await;
-    // return;
+    if args.list_attempts {
+        strategy::strategy::list_attempts(&pool, 50).await;
+        return;
+    }
 
-    let securities = get_securities(&pool, &args).await;
+    if let Some(attempt) = &args.show_attempt {
+        strategy::strategy::show_attempt(&pool, attempt, args.json).await;
+        return;
+    }
+
+    if let Some(attempt) = &args.report {
+        strategy::strategy::attempt_report(&pool, attempt, args.json).await;
+        return;
+    }
 
     if args.kind.as_str() != "none" {
         let kind = Kind::from(args.kind.as_str());
@@ -243,36 +426,14 @@ pub async fn draw_candles(candles: Vec<Candle>, security: &str, file_name: &str)
     }
     let file_name = file_name.replace(".csv", ".png");
     let file = Path::new(&dir).join(file_name);
-    let root = BitMapBackend::new(&file, (1024, 768)).into_drawing_area();
-    root.fill(&WHITE).unwrap();
-
-    let to_date = candles.last().unwrap().end + Duration::minutes(10);
-    let from_date = candles[0].end - Duration::minutes(10);
-
-    let mut chart = ChartBuilder::on(&root)
-        .x_label_area_size(40)
-        .y_label_area_size(40)
-        .caption(security, ("sans-serif", 50.0).into_font())
-        .build_cartesian_2d(RangedDateTime::from(from_date..to_date), 210f32..225f32)
-        .unwrap();
-
-    chart.configure_mesh().draw().unwrap();
-
-    let candles = candles.iter().map(|x| {
-        CandleStick::new(
-            x.begin,
-            x.open,
-            x.high,
-            x.low,
-            x.close,
-            GREEN.filled(),
-            RED.filled(),
-            7,
-        )
-    });
-
-    chart.draw_series(candles).unwrap();
-    root.present().unwrap();
+    chart::save_png(
+        &file,
+        security,
+        &candles,
+        &ChartLayers::default(),
+        (1024, 768),
+    )
+    .expect("failed to draw candles");
 }
 
 pub async fn draw_graphs(security: &str) -> Result<()> {
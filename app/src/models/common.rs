@@ -1,7 +1,9 @@
-use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use serde::Deserialize;
 use sqlx;
 use sqlx::types::Uuid;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::mem;
 
 pub mod unix_timestamp {
@@ -28,7 +30,7 @@ impl Into<String> for SecuritiesStr {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Frame {
     M1,
     M15,
@@ -171,7 +173,7 @@ impl ToSql for Trade {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, sqlx::FromRow)]
+#[derive(Debug, Default, Clone, sqlx::FromRow)]
 pub struct TradeView {
     pub trade_period: NaiveDateTime,
     pub buysell: String,
@@ -206,9 +208,12 @@ pub enum AvgPeriod {
     Month,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OperationType {
     Buy,
     Sold,
+    Short,
+    Cover,
 }
 
 impl From<&str> for OperationType {
@@ -216,6 +221,8 @@ impl From<&str> for OperationType {
         match value {
             "buy" => Self::Buy,
             "sold" => Self::Sold,
+            "short" => Self::Short,
+            "cover" => Self::Cover,
             _ => unimplemented!("operation type: {} not implemented", value),
         }
     }
@@ -226,10 +233,13 @@ impl ToString for OperationType {
         match self {
             OperationType::Buy => String::from("buy"),
             OperationType::Sold => String::from("sold"),
+            OperationType::Short => String::from("short"),
+            OperationType::Cover => String::from("cover"),
         }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Operation {
     pub id: Uuid,
     pub attempt: Uuid,
@@ -243,26 +253,202 @@ pub struct Operation {
     pub sum_after: f32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawingKind {
+    Level,
+    Line,
+    Rect,
+    Note,
+}
+
+impl From<&str> for DrawingKind {
+    fn from(value: &str) -> Self {
+        match value {
+            "level" => Self::Level,
+            "line" => Self::Line,
+            "rect" => Self::Rect,
+            "note" => Self::Note,
+            _ => unimplemented!("drawing kind: {} not implemented", value),
+        }
+    }
+}
+
+impl fmt::Display for DrawingKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawingKind::Level => write!(f, "level"),
+            DrawingKind::Line => write!(f, "line"),
+            DrawingKind::Rect => write!(f, "rect"),
+            DrawingKind::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// Mark-up of the terminal chart anchored to (time, price), kept per security
+#[derive(Debug, Clone)]
+pub struct Drawing {
+    pub id: Uuid,
+    pub security: String,
+    pub kind: DrawingKind,
+    pub begin: NaiveDateTime,
+    pub begin_price: f32,
+    /// second anchor of lines and rectangles, same as the first for the others
+    pub end: NaiveDateTime,
+    pub end_price: f32,
+    pub text: String,
+}
+
+/// Named strategy settings, e.g. `profit`, `break_volume`
+pub type Params = BTreeMap<String, f32>;
+
+#[derive(Debug, Clone)]
 pub struct Attempt {
     pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub strategy: String,
+    pub securities: Vec<String>,
+    pub begin: Option<NaiveDateTime>,
+    pub end: Option<NaiveDateTime>,
+    pub frame: String,
+    /// app version and git revision
+    pub version: String,
     pub profit: f32,
     pub commission: f32,
+    /// percent per day of the short position value
+    pub borrow_fee: f32,
+    /// fill model of the backtest
+    pub fill: String,
+    pub params: Params,
+    /// final metrics of the report
+    pub metrics: serde_json::Value,
 }
 
-#[allow(dead_code)]
-struct Wallet {
-    balance: f32,
+/// Walk-forward fold: settings tuned on in-sample days,
+/// checked on the following out-of-sample days
+pub struct Fold {
+    pub id: Uuid,
+    pub run: Uuid,
+    /// out-of-sample attempt
+    pub attempt: Uuid,
+    pub fold: i32,
+    pub security: String,
+    pub in_begin: NaiveDateTime,
+    pub in_end: NaiveDateTime,
+    pub out_begin: NaiveDateTime,
+    pub out_end: NaiveDateTime,
+    pub in_return: f32,
+    pub out_return: f32,
+    pub out_drawdown: f32,
+    pub out_sharpe: f32,
 }
 
+/// Position size of a portfolio entry
+#[derive(Debug, Clone, Copy)]
+pub enum Sizing {
+    /// equity divided by max positions or by the number of securities
+    EqualWeight,
+    /// percent of equity
+    FixedFraction(f32),
+    /// percent of equity at risk over the average candle range
+    Volatility(f32),
+}
+
+/// Cash shared by all securities of a portfolio backtest
+#[derive(Debug, Clone)]
+pub struct Wallet {
+    pub balance: f32,
+    pub sizing: Sizing,
+    pub max_positions: Option<usize>,
+}
+
+/// Exit rules evaluated by the backtest regardless of the strategy signal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExitRules {
+    /// percent against the entry price
+    pub stop_loss: Option<f32>,
+    /// percent against the best price since entry
+    pub trailing_stop: Option<f32>,
+    pub max_hold: Option<Duration>,
+    /// close position at this time, no entries after it
+    pub session_end: Option<NaiveTime>,
+}
+
+impl ExitRules {
+    /// Keys of the rules in the params of an attempt
+    pub const PARAMS: [&str; 4] = ["stop_loss", "trailing_stop", "max_hold", "session_end"];
+
+    /// Rules that are set, `max_hold` in minutes, `session_end` in minutes since midnight
+    pub fn params(&self) -> Params {
+        let values = [
+            self.stop_loss,
+            self.trailing_stop,
+            self.max_hold.map(|a| a.num_minutes() as f32),
+            self.session_end
+                .map(|a| (a.num_seconds_from_midnight() / 60) as f32),
+        ];
+        Self::PARAMS
+            .iter()
+            .zip(values)
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect()
+    }
+}
+
+/// How orders of the backtest are filled
+#[derive(Debug, Clone, Copy, Default)]
+pub enum FillModel {
+    /// whole order at the open of the next candle
+    #[default]
+    Open,
+    /// walk the recorded trades after the signal
+    Trades {
+        latency: Duration,
+        /// percent against the order
+        slippage: f32,
+        /// percent of every trade volume available to the order
+        participation: f32,
+    },
+}
+
+impl fmt::Display for FillModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FillModel::Open => write!(f, "open"),
+            FillModel::Trades {
+                latency,
+                slippage,
+                participation,
+            } => write!(
+                f,
+                "trades latency={}ms slippage={}% participation={}%",
+                latency.num_milliseconds(),
+                slippage,
+                participation
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Packet {
     pub security: String,
     pub min_count: i32,
+    /// negative for a short position
     pub purchased: i32,
     pub profit: f32,
     pub balance: f32,
+    pub entry_price: f32,
+    pub entry_time: Option<NaiveDateTime>,
+    /// best price since entry: highest for long, lowest for short
+    pub peak: f32,
+    pub exits: ExitRules,
+    /// percent per day of the short position value, charged on cover
+    pub borrow_fee: f32,
+    pub fill: FillModel,
+    /// cash available for the next entry, the whole balance if not set
+    pub budget: Option<f32>,
 }
 
-#[allow(dead_code)]
 impl Packet {
     pub fn new(security: &str, min_count: i32, balance: f32) -> Self {
         Self {
@@ -271,10 +457,26 @@ impl Packet {
             purchased: 0,
             profit: 0.0,
             balance,
+            entry_price: 0.0,
+            entry_time: None,
+            peak: 0.0,
+            exits: ExitRules::default(),
+            borrow_fee: 0.0,
+            fill: FillModel::default(),
+            budget: None,
         }
     }
 }
 
+/// Single recorded trade, `buysell` is the side of the aggressor
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct Tick {
+    pub time: NaiveDateTime,
+    pub price: f32,
+    pub quantity: i32,
+    pub buysell: String,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize, sqlx::FromRow, Clone)]
 pub struct TradeInfo {
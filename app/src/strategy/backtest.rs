@@ -0,0 +1,608 @@
+use crate::db::pg;
+use crate::models::common::{
+    Attempt, Candle, Frame, Operation, OperationType, Packet, Tick, TradeInfo,
+};
+use crate::strategy::base::{Signal, Strategy};
+use crate::strategy::fill::{Fill, Order, fill_order, trigger_time};
+use crate::strategy::report::Report;
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+pub const COMMISSION: f32 = 0.04;
+/// Cash of every security at the start of a backtest
+pub const START_BALANCE: f32 = 100_000.0;
+/// Shares in one lot, lot sizes of the securities are not stored yet
+pub const MIN_COUNT: i32 = 1;
+pub const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (", env!("GIT_REV"), ")");
+
+/// Result of a backtest run, nothing is written to the database
+pub struct BacktestResult {
+    pub attempt: Attempt,
+    pub operations: Vec<Operation>,
+    /// balance plus open positions at the close of every candle
+    pub equity: Vec<(NaiveDateTime, f32)>,
+    /// final state of every traded security
+    pub packets: Vec<Packet>,
+}
+
+/// Runs the strategy over candles in memory.
+///
+/// `trades` may be empty if the strategy does not use them, `ticks` may be
+/// empty unless the packet fills orders from the recorded trades.
+/// Signals are executed during the next candle, exit rules
+/// of the packet are checked first on every candle.
+pub fn backtest(
+    strategy: &mut dyn Strategy,
+    packet: Packet,
+    candles: &[Candle],
+    trades: &[TradeInfo],
+    ticks: &[Tick],
+) -> BacktestResult {
+    let attempt = new_attempt(strategy, &packet, candles);
+    let mut equity: Vec<(NaiveDateTime, f32)> = Vec::with_capacity(candles.len());
+    if let Some(first) = candles.first() {
+        equity.push((first.begin, packet.balance));
+    }
+
+    let mut runner = Runner::new(strategy, packet, attempt, candles, trades, ticks);
+    while let Some(next) = runner.step() {
+        let packet = &runner.packet;
+        equity.push((
+            next.begin,
+            packet.balance + packet.purchased as f32 * next.close,
+        ));
+    }
+
+    BacktestResult {
+        attempt: runner.attempt,
+        operations: runner.operations,
+        equity,
+        packets: vec![runner.packet],
+    }
+}
+
+/// Attempt of a single security backtest, params hold the exit rules as well
+pub fn new_attempt(strategy: &dyn Strategy, packet: &Packet, candles: &[Candle]) -> Attempt {
+    let mut params = strategy.params();
+    params.extend(packet.exits.params());
+    Attempt {
+        id: Uuid::new_v4(),
+        created_at: Local::now().naive_local(),
+        strategy: strategy.name().to_string(),
+        securities: vec![packet.security.clone()],
+        begin: candles.first().map(|a| a.begin),
+        end: candles.last().map(|a| a.end),
+        frame: String::new(),
+        version: VERSION.to_string(),
+        profit: strategy.profit(),
+        commission: COMMISSION,
+        borrow_fee: packet.borrow_fee,
+        fill: packet.fill.to_string(),
+        params,
+        metrics: serde_json::Value::Null,
+    }
+}
+
+/// Backtest state of one security, moved forward candle by candle
+pub struct Runner<'a> {
+    strategy: &'a mut dyn Strategy,
+    pub packet: Packet,
+    pub attempt: Attempt,
+    pub candles: &'a [Candle],
+    trades: &'a [TradeInfo],
+    ticks: &'a [Tick],
+    pub operations: Vec<Operation>,
+    /// candle to process on the next step
+    pub index: usize,
+    current_date: Option<NaiveDate>,
+}
+
+impl<'a> Runner<'a> {
+    pub fn new(
+        strategy: &'a mut dyn Strategy,
+        packet: Packet,
+        attempt: Attempt,
+        candles: &'a [Candle],
+        trades: &'a [TradeInfo],
+        ticks: &'a [Tick],
+    ) -> Self {
+        Self {
+            strategy,
+            packet,
+            attempt,
+            candles,
+            trades,
+            ticks,
+            operations: vec![],
+            index: 0,
+            current_date: None,
+        }
+    }
+
+    /// Candle of the next step, the last candle is never processed
+    /// because its signals could not be executed
+    pub fn current(&self) -> Option<&'a Candle> {
+        if self.index + 1 < self.candles.len() {
+            self.candles.get(self.index)
+        } else {
+            None
+        }
+    }
+
+    /// Processes the current candle and returns the next one,
+    /// where the signals were executed
+    pub fn step(&mut self) -> Option<&'a Candle> {
+        let candle = self.current()?;
+        let next = &self.candles[self.index + 1];
+        self.index += 1;
+
+        let (packet, attempt, ticks) = (&mut self.packet, &self.attempt, self.ticks);
+        let operations = &mut self.operations;
+        // правила выхода проверяются внутри свечи до сигналов стратегии
+        if let Some(price) = exit_price(packet, candle) {
+            let long = packet.purchased > 0;
+            let signal = if long { Signal::Sold } else { Signal::Cover };
+            let from = trigger_time(ticks, candle.begin, candle.end, price, long);
+            execute(
+                packet,
+                attempt,
+                ticks,
+                signal,
+                price,
+                (from, candle.end),
+                operations,
+            );
+        }
+        if packet.purchased > 0 {
+            packet.peak = f32::max(packet.peak, candle.high);
+        }
+        if packet.purchased < 0 {
+            packet.peak = f32::min(packet.peak, candle.low);
+        }
+
+        let date = candle.begin.date();
+        if self.current_date != Some(date) {
+            self.current_date = Some(date);
+            let signal = self.strategy.on_day_start(date, packet);
+            execute_next(packet, next, attempt, ticks, signal, operations);
+        }
+
+        if self.strategy.uses_trades() {
+            for info in self.trades.iter().filter(|a| a.begin == candle.begin) {
+                let signal = self.strategy.on_trade(info, packet);
+                execute_next(packet, next, attempt, ticks, signal, operations);
+            }
+        }
+
+        let signal = self.strategy.on_candle(candle, packet);
+        execute_next(packet, next, attempt, ticks, signal, operations);
+        Some(next)
+    }
+}
+
+/// Persists attempt with final metrics and its operations in one transaction,
+/// an attempt is never saved without a part of its operations
+pub async fn save_result(pool: &PgPool, result: &BacktestResult, frame: &Frame) {
+    let attempt = Attempt {
+        frame: frame.to_string(),
+        metrics: serde_json::to_value(Report::from_result(result))
+            .expect("failed to serialize report"),
+        ..result.attempt.clone()
+    };
+    let mut tx = pool.begin().await.expect("failed to begin transaction");
+    pg::add_attempt(&mut tx, &attempt).await;
+    let mut prev: Option<Uuid> = None;
+    for operation in result.operations.iter() {
+        pg::add_operation(&mut tx, operation, prev).await;
+        prev = Some(operation.id);
+    }
+    tx.commit().await.expect("failed to commit attempt");
+}
+
+/// Price of the forced exit if any exit rule of the packet fires on the candle
+fn exit_price(packet: &Packet, candle: &Candle) -> Option<f32> {
+    if packet.purchased == 0 {
+        return None;
+    }
+    let rules = &packet.exits;
+    if let Some(session_end) = rules.session_end
+        && candle.begin.time() >= session_end
+    {
+        return Some(candle.open);
+    }
+    if let (Some(max_hold), Some(entry_time)) = (rules.max_hold, packet.entry_time)
+        && candle.begin - entry_time >= max_hold
+    {
+        return Some(candle.open);
+    }
+
+    // для шорта стоп выше цены входа
+    let side = packet.purchased.signum() as f32;
+    let mut stop: Option<f32> = None;
+    if let Some(percent) = rules.stop_loss {
+        stop = Some(packet.entry_price * (1.0 - side * percent / 100.0));
+    }
+    if let Some(percent) = rules.trailing_stop {
+        let trailing = packet.peak * (1.0 - side * percent / 100.0);
+        stop = Some(stop.map_or(trailing, |a| {
+            if side > 0.0 {
+                f32::max(a, trailing)
+            } else {
+                f32::min(a, trailing)
+            }
+        }));
+    }
+    match stop {
+        // гэп за стопом исполняется по открытию
+        Some(stop) if side > 0.0 && candle.low <= stop => Some(f32::min(stop, candle.open)),
+        Some(stop) if side < 0.0 && candle.high >= stop => Some(f32::max(stop, candle.open)),
+        _ => None,
+    }
+}
+
+/// Signals are executed during the next candle
+fn execute_next(
+    packet: &mut Packet,
+    next: &Candle,
+    attempt: &Attempt,
+    ticks: &[Tick],
+    signal: Signal,
+    operations: &mut Vec<Operation>,
+) {
+    if matches!(signal, Signal::Buy | Signal::Short)
+        && let Some(session_end) = packet.exits.session_end
+        && next.begin.time() >= session_end
+    {
+        return;
+    }
+    execute(
+        packet,
+        attempt,
+        ticks,
+        signal,
+        next.open,
+        (next.begin, next.end),
+        operations,
+    );
+}
+
+/// Places an order expected at `price`, filled by the model of the packet
+/// within `window`
+pub fn execute(
+    packet: &mut Packet,
+    attempt: &Attempt,
+    ticks: &[Tick],
+    signal: Signal,
+    price: f32,
+    window: (NaiveDateTime, NaiveDateTime),
+    operations: &mut Vec<Operation>,
+) {
+    let (operation_type, buy) = match (packet.purchased.signum(), signal) {
+        // выходим
+        (1, Signal::Sold) => (OperationType::Sold, false),
+        (-1, Signal::Cover) => (OperationType::Cover, true),
+        // находим точку входа
+        (0, Signal::Buy) => (OperationType::Buy, true),
+        (0, Signal::Short) => (OperationType::Short, false),
+        _ => return,
+    };
+    let count = if packet.purchased != 0 {
+        packet.purchased.abs()
+    } else {
+        affordable(packet, attempt, price)
+    };
+    if count <= 0 {
+        return;
+    }
+    let order = Order {
+        buy,
+        count,
+        price,
+        from: window.0,
+        till: window.1,
+    };
+    let fill = match fill_order(&packet.fill, ticks, &order) {
+        Some(fill) => fill,
+        None => return,
+    };
+    let operation = match operation_type {
+        OperationType::Buy | OperationType::Short => open(packet, attempt, operation_type, fill),
+        OperationType::Sold | OperationType::Cover => {
+            Some(close(packet, attempt, operation_type, fill))
+        }
+    };
+    operations.extend(operation);
+}
+
+/// Count for the whole balance or budget at `price`, commission included
+fn affordable(packet: &Packet, attempt: &Attempt, price: f32) -> i32 {
+    let balance = packet
+        .budget
+        .map_or(packet.balance, |a| f32::min(a, packet.balance));
+    let mut count = f32::floor(balance / price) as i32;
+    while count > 0 {
+        let commission: f32 = ((count as f32 * price) / 100.0) * attempt.commission;
+        if (count as f32 * price) + commission <= balance {
+            break;
+        }
+        count -= 1;
+    }
+    (count / packet.min_count) * packet.min_count
+}
+
+/// Opens long or short position with the filled count
+fn open(
+    packet: &mut Packet,
+    attempt: &Attempt,
+    operation_type: OperationType,
+    fill: Fill,
+) -> Option<Operation> {
+    // проскальзывание может съесть часть баланса
+    let count = i32::min(fill.count, affordable(packet, attempt, fill.price));
+    let count = (count / packet.min_count) * packet.min_count;
+    if count <= 0 {
+        return None;
+    }
+    let price = fill.price;
+    let commission: f32 = ((count as f32 * price) / 100.0) * attempt.commission;
+    let short = matches!(operation_type, OperationType::Short);
+    packet.purchased = if short { -count } else { count };
+    packet.profit = if short {
+        price - (price / 100.0) * attempt.profit
+    } else {
+        (price / 100.0) * attempt.profit + price
+    };
+    packet.entry_price = price;
+    packet.entry_time = Some(fill.time);
+    packet.peak = price;
+    Some(create_operation(
+        attempt,
+        operation_type,
+        packet,
+        count,
+        commission,
+        price,
+        fill.time,
+    ))
+}
+
+/// Closes the filled part of the position,
+/// borrow fee of a short is added to the commission
+fn close(
+    packet: &mut Packet,
+    attempt: &Attempt,
+    operation_type: OperationType,
+    fill: Fill,
+) -> Operation {
+    let count = fill.count as f32;
+    let mut commission: f32 = ((count * fill.price) / 100.0) * attempt.commission;
+    if packet.purchased < 0
+        && let Some(entry_time) = packet.entry_time
+    {
+        let days = (fill.time - entry_time).num_seconds() as f32 / 86_400.0;
+        commission += ((count * packet.entry_price) / 100.0) * attempt.borrow_fee * days;
+    }
+    let operation = create_operation(
+        attempt,
+        operation_type,
+        packet,
+        fill.count,
+        commission,
+        fill.price,
+        fill.time,
+    );
+    packet.purchased -= packet.purchased.signum() * fill.count;
+    if packet.purchased == 0 {
+        packet.entry_time = None;
+    }
+    operation
+}
+
+fn create_operation(
+    attempt: &Attempt,
+    operation_type: OperationType,
+    packet: &mut Packet,
+    count: i32,
+    commission: f32,
+    price: f32,
+    time_at: NaiveDateTime,
+) -> Operation {
+    let sum_after: f32 = match operation_type {
+        OperationType::Buy | OperationType::Cover => packet.balance - (count as f32 * price),
+        OperationType::Sold | OperationType::Short => packet.balance + (count as f32 * price),
+    } - commission;
+    let operation = Operation {
+        id: Uuid::new_v4(),
+        attempt: attempt.id,
+        operation_type,
+        security: packet.security.clone(),
+        count,
+        price,
+        commission,
+        time_at,
+        sum_before: packet.balance,
+        sum_after,
+    };
+    packet.balance = sum_after;
+    operation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::Params;
+    use chrono::{Duration, NaiveDate};
+
+    /// Returns the signals in order, one per processed candle
+    #[derive(Clone)]
+    struct Script {
+        signals: Vec<Signal>,
+        index: usize,
+    }
+
+    impl Strategy for Script {
+        fn name(&self) -> &'static str {
+            "script"
+        }
+
+        fn clone_box(&self) -> Box<dyn Strategy> {
+            Box::new(self.clone())
+        }
+
+        fn profit(&self) -> f32 {
+            0.0
+        }
+
+        fn params(&self) -> Params {
+            Params::new()
+        }
+
+        fn set_param(&mut self, _name: &str, _value: f32) -> bool {
+            false
+        }
+
+        fn on_candle(&mut self, _candle: &Candle, _packet: &Packet) -> Signal {
+            let signal = self
+                .signals
+                .get(self.index)
+                .copied()
+                .unwrap_or(Signal::Hold);
+            self.index += 1;
+            signal
+        }
+    }
+
+    fn script(signals: &[Signal]) -> Script {
+        Script {
+            signals: signals.to_vec(),
+            index: 0,
+        }
+    }
+
+    /// m1 candles with the given opens, close is open + 1
+    fn candles(opens: &[f32]) -> Vec<Candle> {
+        let start = NaiveDate::from_ymd_opt(2025, 3, 3)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        opens
+            .iter()
+            .enumerate()
+            .map(|(i, open)| {
+                let begin = start + Duration::minutes(i as i64);
+                Candle {
+                    open: *open,
+                    close: open + 1.0,
+                    high: open + 2.0,
+                    low: open - 1.0,
+                    value: 0.0,
+                    volume: 0.0,
+                    begin,
+                    end: begin + Duration::seconds(59),
+                    position_x: None,
+                    position_y: None,
+                }
+            })
+            .collect()
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{a} != {b}");
+    }
+
+    #[test]
+    fn signal_fills_at_next_open() {
+        let candles = candles(&[100.0, 101.0, 105.0, 107.0, 110.0]);
+        let mut strategy = script(&[Signal::Hold, Signal::Buy, Signal::Hold, Signal::Sold]);
+        let packet = Packet::new("SBER", 1, 10_000.0);
+        let result = backtest(&mut strategy, packet, &candles, &[], &[]);
+
+        assert_eq!(result.operations.len(), 2);
+        let (buy, sold) = (&result.operations[0], &result.operations[1]);
+        assert!(matches!(buy.operation_type, OperationType::Buy));
+        assert_eq!(buy.price, candles[2].open);
+        assert_eq!(buy.time_at, candles[2].begin);
+        assert!(matches!(sold.operation_type, OperationType::Sold));
+        assert_eq!(sold.price, candles[4].open);
+        assert_eq!(sold.time_at, candles[4].begin);
+    }
+
+    #[test]
+    fn last_candle_is_not_processed() {
+        let candles = candles(&[100.0, 101.0]);
+        let mut strategy = script(&[Signal::Hold, Signal::Buy]);
+        let packet = Packet::new("SBER", 1, 10_000.0);
+        let result = backtest(&mut strategy, packet, &candles, &[], &[]);
+
+        assert!(result.operations.is_empty());
+        assert_eq!(strategy.index, 1);
+    }
+
+    #[test]
+    fn affordable_counts_commission_and_lots() {
+        let strategy = script(&[]);
+        let packet = Packet::new("SBER", 1, 1_000.0);
+        let attempt = new_attempt(&strategy, &packet, &[]);
+        // 10 * 100 + 0.04% commission is more than the balance
+        assert_eq!(affordable(&packet, &attempt, 100.0), 9);
+
+        let packet = Packet::new("SBER", 5, 1_000.0);
+        assert_eq!(affordable(&packet, &attempt, 100.0), 5);
+
+        let mut packet = Packet::new("SBER", 1, 1_000.0);
+        packet.budget = Some(450.0);
+        assert_eq!(affordable(&packet, &attempt, 100.0), 4);
+
+        let packet = Packet::new("SBER", 10, 900.0);
+        assert_eq!(affordable(&packet, &attempt, 100.0), 0);
+    }
+
+    #[test]
+    fn short_open_and_cover_balance() {
+        let candles = candles(&[100.0, 100.0, 100.0, 90.0, 90.0]);
+        let mut strategy = script(&[Signal::Hold, Signal::Short, Signal::Cover]);
+        let packet = Packet::new("SBER", 1, 1_000.0);
+        let result = backtest(&mut strategy, packet, &candles, &[], &[]);
+
+        assert_eq!(result.operations.len(), 2);
+        let (short, cover) = (&result.operations[0], &result.operations[1]);
+        assert!(matches!(short.operation_type, OperationType::Short));
+        assert_eq!(short.count, 9);
+        assert_eq!(short.price, 100.0);
+        let short_commission = 9.0 * 100.0 / 100.0 * COMMISSION;
+        assert_near(short.commission, short_commission);
+        // продажа в шорт увеличивает баланс
+        assert_near(short.sum_after, 1_000.0 + 900.0 - short_commission);
+
+        assert!(matches!(cover.operation_type, OperationType::Cover));
+        assert_eq!(cover.count, 9);
+        assert_eq!(cover.price, 90.0);
+        let cover_commission = 9.0 * 90.0 / 100.0 * COMMISSION;
+        assert_near(cover.commission, cover_commission);
+        assert_near(cover.sum_before, short.sum_after);
+        assert_near(cover.sum_after, short.sum_after - 810.0 - cover_commission);
+
+        let packet = &result.packets[0];
+        assert_eq!(packet.purchased, 0);
+        assert_near(
+            packet.balance,
+            1_000.0 + 90.0 - short_commission - cover_commission,
+        );
+    }
+
+    #[test]
+    fn short_cover_charges_borrow_fee() {
+        let candles = candles(&[100.0, 100.0, 100.0, 100.0]);
+        let mut strategy = script(&[Signal::Short, Signal::Cover]);
+        let mut packet = Packet::new("SBER", 1, 1_000.0);
+        packet.borrow_fee = 100.0;
+        let result = backtest(&mut strategy, packet, &candles, &[], &[]);
+
+        let cover = &result.operations[1];
+        // одна минута в шорте
+        let days = 60.0 / 86_400.0;
+        let fee = 9.0 * 100.0 / 100.0 * 100.0 * days;
+        assert_near(cover.commission, 9.0 * 100.0 / 100.0 * COMMISSION + fee);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::models::common::{Candle, Packet, Params, TradeInfo};
+use chrono::NaiveDate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Buy,
+    Sold,
+    Short,
+    Cover,
+    Hold,
+}
+
+/// Trading strategy driven by the backtest loop.
+///
+/// Every hook receives the current state of the packet and returns a signal,
+/// which is executed at the open of the next candle.
+pub trait Strategy: Send {
+    fn name(&self) -> &'static str;
+
+    /// Copy with the same settings and state, the sweep clones one strategy
+    /// per combination instead of creating every one from the database
+    fn clone_box(&self) -> Box<dyn Strategy>;
+
+    /// Take profit in percent from the entry price
+    fn profit(&self) -> f32;
+
+    /// Current values of the tunable settings
+    fn params(&self) -> Params;
+
+    /// Returns false if the strategy has no such setting
+    fn set_param(&mut self, name: &str, value: f32) -> bool;
+
+    /// Whether the backtest should load trades and call `on_trade`
+    fn uses_trades(&self) -> bool {
+        false
+    }
+
+    fn on_day_start(&mut self, _date: NaiveDate, _packet: &Packet) -> Signal {
+        Signal::Hold
+    }
+
+    fn on_candle(&mut self, candle: &Candle, packet: &Packet) -> Signal;
+
+    fn on_trade(&mut self, _info: &TradeInfo, _packet: &Packet) -> Signal {
+        Signal::Hold
+    }
+}
+
+/// Common exit rule: close position when take profit is reached
+pub fn take_profit(candle: &Candle, packet: &Packet) -> Signal {
+    if packet.purchased > 0 && candle.close >= packet.profit {
+        Signal::Sold
+    } else if packet.purchased < 0 && candle.close <= packet.profit {
+        Signal::Cover
+    } else {
+        Signal::Hold
+    }
+}
+
+/// Entry signal in the direction chosen by the `short` setting
+pub fn entry(short: bool) -> Signal {
+    if short { Signal::Short } else { Signal::Buy }
+}
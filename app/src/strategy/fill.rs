@@ -0,0 +1,99 @@
+use crate::models::common::{FillModel, Tick};
+use chrono::NaiveDateTime;
+
+/// Market order placed by a signal or an exit rule
+pub struct Order {
+    pub buy: bool,
+    pub count: i32,
+    /// expected price, used as is by the open model
+    pub price: f32,
+    pub from: NaiveDateTime,
+    pub till: NaiveDateTime,
+}
+
+/// Filled part of the order
+#[derive(Debug)]
+pub struct Fill {
+    pub count: i32,
+    /// average price, slippage included
+    pub price: f32,
+    /// time of the first filled trade
+    pub time: NaiveDateTime,
+}
+
+/// Fills the order according to the model, `None` if nothing is filled.
+///
+/// The trades model takes a share of every trade of the same aggressor side
+/// after the latency, until the order is filled or `till` is reached.
+pub fn fill_order(model: &FillModel, ticks: &[Tick], order: &Order) -> Option<Fill> {
+    let (latency, slippage, participation) = match model {
+        FillModel::Open => {
+            return Some(Fill {
+                count: order.count,
+                price: order.price,
+                time: order.from,
+            });
+        }
+        FillModel::Trades {
+            latency,
+            slippage,
+            participation,
+        } => (*latency, *slippage, *participation),
+    };
+
+    let side = if order.buy { "B" } else { "S" };
+    let from = order.from + latency;
+    let start = ticks.partition_point(|a| a.time < from);
+
+    let mut count = 0;
+    let mut sum = 0.0;
+    let mut time: Option<NaiveDateTime> = None;
+    for tick in ticks[start..].iter().take_while(|a| a.time <= order.till) {
+        if tick.buysell != side {
+            continue;
+        }
+        let available = (tick.quantity as f32 * participation / 100.0).floor() as i32;
+        let take = i32::min(available, order.count - count);
+        if take <= 0 {
+            continue;
+        }
+        count += take;
+        sum += take as f32 * tick.price;
+        time.get_or_insert(tick.time);
+        if count == order.count {
+            break;
+        }
+    }
+
+    let time = time?;
+    let price = sum / count as f32;
+    let price = if order.buy {
+        price * (1.0 + slippage / 100.0)
+    } else {
+        price * (1.0 - slippage / 100.0)
+    };
+    Some(Fill { count, price, time })
+}
+
+/// Time of the first trade at or beyond `price` within the candle,
+/// used as the moment a stop is triggered
+pub fn trigger_time(
+    ticks: &[Tick],
+    begin: NaiveDateTime,
+    end: NaiveDateTime,
+    price: f32,
+    long: bool,
+) -> NaiveDateTime {
+    let start = ticks.partition_point(|a| a.time < begin);
+    ticks[start..]
+        .iter()
+        .take_while(|a| a.time <= end)
+        .find(|a| {
+            if long {
+                a.price <= price
+            } else {
+                a.price >= price
+            }
+        })
+        .map_or(begin, |a| a.time)
+}
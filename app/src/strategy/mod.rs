@@ -1 +1,10 @@
+pub mod backtest;
+pub mod base;
+pub mod fill;
+pub mod portfolio;
+pub mod registry;
+pub mod report;
+pub mod strategies;
 pub mod strategy;
+pub mod sweep;
+pub mod walk_forward;
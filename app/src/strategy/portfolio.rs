@@ -0,0 +1,362 @@
+use crate::db::pg;
+use crate::models::common::{Attempt, Candle, Sizing, Tick, TradeInfo, Wallet};
+use crate::strategy::backtest::{BacktestResult, Runner, new_attempt, save_result};
+use crate::strategy::base::Strategy;
+use crate::strategy::registry;
+use crate::strategy::report::Report;
+use crate::strategy::strategy::{
+    CANDLES_LIMIT, Settings, load_ticks, load_trade_info, print_report,
+};
+use chrono::NaiveDateTime;
+use log::{error, info};
+use sqlx::postgres::PgPool;
+use std::collections::BTreeSet;
+
+/// Candles used to measure volatility for sizing
+const VOLATILITY_CANDLES: usize = 20;
+
+/// Market data of one security of the portfolio
+pub struct Leg {
+    pub strategy: Box<dyn Strategy>,
+    pub security: String,
+    pub candles: Vec<Candle>,
+    pub trades: Vec<TradeInfo>,
+    pub ticks: Vec<Tick>,
+}
+
+pub async fn run_portfolio(
+    pool: &PgPool,
+    name: &str,
+    securities: &Vec<String>,
+    wallet: Wallet,
+    settings: &Settings,
+) {
+    let (begin, end) = (settings.begin, settings.end);
+    if securities.is_empty() {
+        error!("portfolio has no securities");
+        return;
+    }
+
+    let mut legs = vec![];
+    for security in securities {
+        let strategy = match registry::create(pool, name, security, begin).await {
+            Some(strategy) => strategy,
+            None => {
+                error!(
+                    "strategy {} not found, available: {}",
+                    name,
+                    registry::STRATEGIES.join(", ")
+                );
+                return;
+            }
+        };
+        let candles =
+            pg::get_candles(pool, security, begin, end, CANDLES_LIMIT, &settings.frame).await;
+        let trades = if strategy.uses_trades() {
+            load_trade_info(pool, security, begin, end).await
+        } else {
+            vec![]
+        };
+        let ticks = load_ticks(pool, security, settings).await;
+        legs.push(Leg {
+            strategy,
+            security: security.to_string(),
+            candles,
+            trades,
+            ticks,
+        });
+    }
+
+    let result = portfolio(&mut legs, wallet, settings);
+    info!(
+        "portfolio => {}, securities: {}, operations: {}",
+        name,
+        securities.len(),
+        result.operations.len()
+    );
+    print_report(&Report::from_result(&result), settings.json);
+    if settings.save {
+        save_result(pool, &result, &settings.frame).await;
+    }
+}
+
+/// Runs all legs on a common timeline against one wallet, `legs` must not
+/// be empty.
+///
+/// Before every candle the packet gets the wallet cash and the budget
+/// of the sizing rule, no budget is given once `max_positions` are open.
+/// The budget never exceeds the cash left after the collateral of the open
+/// shorts, so the proceeds of a short can't be spent by other legs.
+pub fn portfolio(legs: &mut [Leg], mut wallet: Wallet, settings: &Settings) -> BacktestResult {
+    let start_balance = wallet.balance;
+    let attempt = portfolio_attempt(legs, settings);
+    let times = legs
+        .iter()
+        .flat_map(|a| a.candles.iter().map(|a| a.begin))
+        .collect::<BTreeSet<NaiveDateTime>>();
+    let max_positions = wallet.max_positions.unwrap_or(legs.len()).max(1);
+
+    let mut runners = legs
+        .iter_mut()
+        .map(|leg| {
+            Runner::new(
+                leg.strategy.as_mut(),
+                settings.packet(&leg.security),
+                attempt.clone(),
+                &leg.candles,
+                &leg.trades,
+                &leg.ticks,
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut closes: Vec<f32> = vec![0.0; runners.len()];
+
+    let mut equity: Vec<(NaiveDateTime, f32)> = vec![];
+    if let Some(first) = times.first() {
+        equity.push((*first, start_balance));
+    }
+    let mut times = times.into_iter().peekable();
+    while let Some(time) = times.next() {
+        for i in 0..runners.len() {
+            if runners[i].current().is_none_or(|a| a.begin != time) {
+                continue;
+            }
+            let total = total_equity(&wallet, &runners, &closes);
+            let open = runners.iter().filter(|a| a.packet.purchased != 0).count();
+            let free = f32::max(wallet.balance - collateral(&runners), 0.0);
+            let runner = &mut runners[i];
+            runner.packet.budget = if open >= max_positions {
+                Some(0.0)
+            } else {
+                Some(f32::min(
+                    budget(&wallet, runner, total, max_positions),
+                    free,
+                ))
+            };
+            runner.packet.balance = wallet.balance;
+            if let Some(next) = runner.step() {
+                closes[i] = next.close;
+            }
+            wallet.balance = runner.packet.balance;
+        }
+        // как в одиночном бэктесте: после исполнения на открытии следующей свечи
+        // позиция оценивается по ее закрытию и с ее временем
+        if let Some(next) = times.peek() {
+            equity.push((*next, total_equity(&wallet, &runners, &closes)));
+        }
+    }
+
+    let mut operations = vec![];
+    let mut packets = vec![];
+    for runner in runners {
+        operations.extend(runner.operations);
+        packets.push(runner.packet);
+    }
+    operations.sort_by_key(|a| a.time_at);
+
+    BacktestResult {
+        attempt,
+        operations,
+        equity,
+        packets,
+    }
+}
+
+/// Common attempt of all legs, `legs` must not be empty
+fn portfolio_attempt(legs: &[Leg], settings: &Settings) -> Attempt {
+    let leg = &legs[0];
+    let mut attempt = new_attempt(
+        leg.strategy.as_ref(),
+        &settings.packet(&leg.security),
+        &leg.candles,
+    );
+    attempt.securities = legs.iter().map(|a| a.security.clone()).collect();
+    attempt.begin = legs
+        .iter()
+        .filter_map(|a| a.candles.first())
+        .map(|a| a.begin)
+        .min();
+    attempt.end = legs
+        .iter()
+        .filter_map(|a| a.candles.last())
+        .map(|a| a.end)
+        .max();
+    attempt
+}
+
+fn total_equity(wallet: &Wallet, runners: &[Runner], closes: &[f32]) -> f32 {
+    wallet.balance
+        + runners
+            .iter()
+            .zip(closes)
+            .map(|(a, close)| a.packet.purchased as f32 * close)
+            .sum::<f32>()
+}
+
+/// Cash held against the open shorts: their proceeds plus the same amount
+/// as margin, the whole position value is backed like a long one
+fn collateral(runners: &[Runner]) -> f32 {
+    runners
+        .iter()
+        .filter(|a| a.packet.purchased < 0)
+        .map(|a| 2.0 * a.packet.entry_price * a.packet.purchased.abs() as f32)
+        .sum()
+}
+
+/// Cash for the next entry of the runner by the sizing rule of the wallet
+fn budget(wallet: &Wallet, runner: &Runner, equity: f32, max_positions: usize) -> f32 {
+    match wallet.sizing {
+        Sizing::EqualWeight => equity / max_positions as f32,
+        Sizing::FixedFraction(percent) => equity * percent / 100.0,
+        Sizing::Volatility(risk) => {
+            let from = (runner.index + 1).saturating_sub(VOLATILITY_CANDLES);
+            let candles = &runner.candles[from..=runner.index];
+            let range = candles
+                .iter()
+                .filter(|a| a.close > 0.0)
+                .map(|a| (a.high - a.low) / a.close)
+                .sum::<f32>()
+                / candles.len() as f32;
+            if range > 0.0 {
+                equity * risk / 100.0 / range
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::{ExitRules, FillModel, Frame};
+    use crate::strategy::backtest::{START_BALANCE, backtest};
+    use crate::strategy::strategies::AvgVolume;
+    use chrono::{Duration, NaiveDate};
+
+    /// m1 candles of (open, close, volume)
+    fn candles(values: &[(f32, f32, f32)]) -> Vec<Candle> {
+        let start = NaiveDate::from_ymd_opt(2025, 3, 3)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, (open, close, volume))| {
+                let begin = start + Duration::minutes(i as i64);
+                Candle {
+                    open: *open,
+                    close: *close,
+                    high: open.max(*close) + 1.0,
+                    low: open.min(*close) - 1.0,
+                    value: 0.0,
+                    volume: *volume,
+                    begin,
+                    end: begin + Duration::seconds(59),
+                    position_x: None,
+                    position_y: None,
+                }
+            })
+            .collect()
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            begin: NaiveDateTime::default(),
+            end: NaiveDateTime::default(),
+            frame: Frame::M1,
+            save: false,
+            json: false,
+            exits: ExitRules::default(),
+            borrow_fee: 0.0,
+            fill: FillModel::Open,
+        }
+    }
+
+    #[test]
+    fn single_leg_equity_matches_backtest() {
+        // красная свеча с объемом выше среднего, выход по тейк-профиту
+        let candles = candles(&[
+            (100.0, 99.0, 500.0),
+            (100.0, 101.0, 0.0),
+            (101.0, 103.0, 0.0),
+            (103.0, 104.0, 0.0),
+            (104.0, 104.5, 0.0),
+        ]);
+        let settings = settings();
+        let mut strategy = AvgVolume::new(100);
+        let single = backtest(&mut strategy, settings.packet("SBER"), &candles, &[], &[]);
+
+        let mut legs = vec![Leg {
+            strategy: Box::new(AvgVolume::new(100)),
+            security: String::from("SBER"),
+            candles: candles.clone(),
+            trades: vec![],
+            ticks: vec![],
+        }];
+        let wallet = Wallet {
+            balance: START_BALANCE,
+            sizing: Sizing::EqualWeight,
+            max_positions: None,
+        };
+        let result = portfolio(&mut legs, wallet, &settings);
+
+        assert_eq!(single.operations.len(), 2);
+        assert_eq!(result.operations.len(), 2);
+        assert_eq!(result.equity.len(), single.equity.len());
+        for (a, b) in result.equity.iter().zip(&single.equity) {
+            assert_eq!(a.0, b.0);
+            assert!((a.1 - b.1).abs() < 0.01, "{} != {}", a.1, b.1);
+        }
+    }
+
+    #[test]
+    fn short_proceeds_are_not_spent_by_other_legs() {
+        // первая бумага шортится на весь капитал, вторая хочет купить следующей свечой
+        let shorted = candles(&[
+            (100.0, 99.0, 500.0),
+            (100.0, 100.5, 0.0),
+            (100.5, 100.2, 0.0),
+            (100.2, 100.4, 0.0),
+            (100.4, 100.6, 0.0),
+        ]);
+        let bought = candles(&[
+            (50.0, 50.5, 0.0),
+            (50.5, 49.8, 500.0),
+            (50.0, 50.3, 0.0),
+            (50.3, 50.5, 0.0),
+            (50.5, 50.6, 0.0),
+        ]);
+        let mut short = AvgVolume::new(100);
+        short.short = true;
+        let mut legs = vec![
+            Leg {
+                strategy: Box::new(short),
+                security: String::from("SBER"),
+                candles: shorted,
+                trades: vec![],
+                ticks: vec![],
+            },
+            Leg {
+                strategy: Box::new(AvgVolume::new(100)),
+                security: String::from("GAZP"),
+                candles: bought,
+                trades: vec![],
+                ticks: vec![],
+            },
+        ];
+        let wallet = Wallet {
+            balance: START_BALANCE,
+            sizing: Sizing::FixedFraction(100.0),
+            max_positions: None,
+        };
+        let result = portfolio(&mut legs, wallet, &settings());
+
+        let (short, long) = (&result.packets[0], &result.packets[1]);
+        assert!(short.purchased < 0);
+        let exposure = short.purchased.abs() as f32 * short.entry_price
+            + long.purchased as f32 * long.entry_price;
+        assert!(exposure <= START_BALANCE, "exposure {exposure}");
+    }
+}
@@ -0,0 +1,26 @@
+use crate::db::pg;
+use crate::models::common::AvgPeriod;
+use crate::strategy::base::Strategy;
+use crate::strategy::strategies::{AvgVolume, BreakVolume, DailyVolume};
+use chrono::{Datelike, NaiveDateTime};
+use sqlx::postgres::PgPool;
+
+pub const STRATEGIES: [&str; 3] = ["avg_volume", "daily_volume", "break_volume"];
+
+pub async fn create(
+    pool: &PgPool,
+    name: &str,
+    security: &str,
+    begin: NaiveDateTime,
+) -> Option<Box<dyn Strategy>> {
+    match name {
+        "avg_volume" => {
+            // находим средний объём торгов за год
+            let avg = pg::get_average_volume(pool, security, AvgPeriod::Year, begin.year()).await;
+            Some(Box::new(AvgVolume::new(avg)))
+        }
+        "daily_volume" => Some(Box::new(DailyVolume::default())),
+        "break_volume" => Some(Box::new(BreakVolume::default())),
+        _ => None,
+    }
+}
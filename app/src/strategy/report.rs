@@ -0,0 +1,390 @@
+use crate::models::common::{Operation, OperationType};
+use crate::strategy::backtest::BacktestResult;
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const TRADING_DAYS: f32 = 252.0;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Report {
+    pub attempt: String,
+    pub start_balance: f32,
+    pub end_balance: f32,
+    /// percent
+    pub total_return: f32,
+    /// percent
+    pub max_drawdown: f32,
+    pub sharpe: f32,
+    pub sortino: f32,
+    /// percent
+    pub win_rate: f32,
+    pub avg_win: f32,
+    pub avg_loss: f32,
+    pub round_trips: usize,
+    /// percent of time with open position
+    pub exposure: f32,
+    pub commission: f32,
+}
+
+impl Report {
+    pub fn from_result(result: &BacktestResult) -> Self {
+        build(
+            &result.attempt.id.to_string(),
+            &result.operations,
+            &result.equity,
+        )
+    }
+
+    /// Equity curve is restored from balances of the operations, so it has points
+    /// only at the exits: drawdown inside a trade is not seen by max drawdown
+    /// and daily returns. `from_result` marks to market on every candle
+    pub fn from_operations(attempt: &str, operations: &[Operation]) -> Self {
+        let mut equity: Vec<(NaiveDateTime, f32)> = vec![];
+        if let Some(first) = operations.first() {
+            equity.push((first.time_at, first.sum_before));
+        }
+        for operation in operations {
+            if let OperationType::Sold | OperationType::Cover = operation.operation_type {
+                equity.push((operation.time_at, operation.sum_after));
+            }
+        }
+        build(attempt, operations, &equity)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize report")
+    }
+
+    pub fn print(&self) {
+        let divider = format!("{:-<40}", "");
+        println!("attempt: {}", self.attempt);
+        println!("{divider}");
+        println!("{:<20}{:>20.2}", "start balance", self.start_balance);
+        println!("{:<20}{:>20.2}", "end balance", self.end_balance);
+        println!("{:<20}{:>19.2}%", "total return", self.total_return);
+        println!("{:<20}{:>19.2}%", "max drawdown", self.max_drawdown);
+        println!("{:<20}{:>20.2}", "sharpe", self.sharpe);
+        println!("{:<20}{:>20.2}", "sortino", self.sortino);
+        println!("{:<20}{:>19.2}%", "win rate", self.win_rate);
+        println!("{:<20}{:>20.2}", "avg win", self.avg_win);
+        println!("{:<20}{:>20.2}", "avg loss", self.avg_loss);
+        println!("{:<20}{:>20}", "round trips", self.round_trips);
+        println!("{:<20}{:>19.2}%", "exposure", self.exposure);
+        println!("{:<20}{:>20.2}", "commission", self.commission);
+        println!("{divider}");
+    }
+}
+
+fn build(attempt: &str, operations: &[Operation], equity: &[(NaiveDateTime, f32)]) -> Report {
+    let start_balance = equity.first().map(|a| a.1).unwrap_or(0.0);
+    let end_balance = equity.last().map(|a| a.1).unwrap_or(start_balance);
+    let total_return = if start_balance > 0.0 {
+        (end_balance - start_balance) / start_balance * 100.0
+    } else {
+        0.0
+    };
+
+    let pnl = round_trips(operations);
+    let wins = pnl
+        .iter()
+        .filter(|a| a.1 > 0.0)
+        .map(|a| a.1)
+        .collect::<Vec<_>>();
+    let losses = pnl
+        .iter()
+        .filter(|a| a.1 <= 0.0)
+        .map(|a| a.1)
+        .collect::<Vec<_>>();
+
+    let in_position = pnl
+        .iter()
+        .fold(TimeDelta::zero(), |acc, a| acc + a.0)
+        .num_seconds() as f32;
+    let span = match (equity.first(), equity.last()) {
+        (Some(first), Some(last)) => (last.0 - first.0).num_seconds() as f32,
+        _ => 0.0,
+    };
+
+    let returns = daily_returns(equity);
+
+    Report {
+        attempt: attempt.to_string(),
+        start_balance,
+        end_balance,
+        total_return,
+        max_drawdown: max_drawdown(equity),
+        sharpe: ratio(&returns, false),
+        sortino: ratio(&returns, true),
+        win_rate: if pnl.is_empty() {
+            0.0
+        } else {
+            wins.len() as f32 / pnl.len() as f32 * 100.0
+        },
+        avg_win: mean(&wins),
+        avg_loss: mean(&losses),
+        round_trips: pnl.len(),
+        exposure: if span > 0.0 {
+            in_position / span * 100.0
+        } else {
+            0.0
+        },
+        commission: operations.iter().map(|a| a.commission).sum(),
+    }
+}
+
+/// Holding time and result of every exit paired with the entry of the same
+/// security, commission included. Partial exits share the entry.
+fn round_trips(operations: &[Operation]) -> Vec<(TimeDelta, f32)> {
+    let mut result = vec![];
+    let mut entries: HashMap<&str, &Operation> = HashMap::new();
+    for operation in operations {
+        match operation.operation_type {
+            OperationType::Buy | OperationType::Short => {
+                entries.insert(&operation.security, operation);
+            }
+            OperationType::Sold | OperationType::Cover => {
+                if let Some(entry) = entries.get(operation.security.as_str()) {
+                    let side = match entry.operation_type {
+                        OperationType::Short => -1.0,
+                        _ => 1.0,
+                    };
+                    let count = operation.count as f32;
+                    let entry_commission = entry.commission * count / entry.count as f32;
+                    result.push((
+                        operation.time_at - entry.time_at,
+                        side * (operation.price - entry.price) * count
+                            - operation.commission
+                            - entry_commission,
+                    ));
+                }
+            }
+        }
+    }
+    result
+}
+
+fn max_drawdown(equity: &[(NaiveDateTime, f32)]) -> f32 {
+    let mut peak = f32::MIN;
+    let mut result = 0.0;
+    for (_, value) in equity {
+        peak = f32::max(peak, *value);
+        if peak > 0.0 {
+            result = f32::max(result, (peak - value) / peak * 100.0);
+        }
+    }
+    result
+}
+
+/// Returns between closing equity of consecutive days
+fn daily_returns(equity: &[(NaiveDateTime, f32)]) -> Vec<f32> {
+    let mut closes: Vec<(NaiveDate, f32)> = vec![];
+    for (time, value) in equity {
+        match closes.last_mut() {
+            Some(last) if last.0 == time.date() => last.1 = *value,
+            _ => closes.push((time.date(), *value)),
+        }
+    }
+    closes
+        .windows(2)
+        .filter(|a| a[0].1 > 0.0)
+        .map(|a| a[1].1 / a[0].1 - 1.0)
+        .collect()
+}
+
+/// Annualized Sharpe, or Sortino when only downside deviation is counted
+fn ratio(returns: &[f32], downside: bool) -> f32 {
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let avg = mean(returns);
+    let deviations = returns
+        .iter()
+        .filter(|a| !downside || **a < 0.0)
+        .map(|a| {
+            if downside {
+                a * a
+            } else {
+                (a - avg) * (a - avg)
+            }
+        })
+        .sum::<f32>();
+    let deviation = f32::sqrt(deviations / (returns.len() - 1) as f32);
+    if deviation == 0.0 {
+        return 0.0;
+    }
+    avg / deviation * f32::sqrt(TRADING_DAYS)
+}
+
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+    use uuid::Uuid;
+
+    fn time(minutes: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 3, 3)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap()
+            + Duration::minutes(minutes)
+    }
+
+    /// Operation `minutes` after the start, balances are set by `with_balances`
+    fn operation(
+        operation_type: OperationType,
+        count: i32,
+        price: f32,
+        commission: f32,
+        minutes: i64,
+    ) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            attempt: Uuid::nil(),
+            operation_type,
+            security: String::from("SBER"),
+            count,
+            price,
+            commission,
+            time_at: time(minutes),
+            sum_before: 0.0,
+            sum_after: 0.0,
+        }
+    }
+
+    /// Fills sum_before/sum_after the way the backtest does
+    fn with_balances(mut operations: Vec<Operation>, balance: f32) -> Vec<Operation> {
+        let mut balance = balance;
+        for operation in operations.iter_mut() {
+            let sum = operation.count as f32 * operation.price;
+            operation.sum_before = balance;
+            balance += match operation.operation_type {
+                OperationType::Buy | OperationType::Cover => -sum,
+                OperationType::Sold | OperationType::Short => sum,
+            } - operation.commission;
+            operation.sum_after = balance;
+        }
+        operations
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{a} != {b}");
+    }
+
+    #[test]
+    fn long_round_trips() {
+        let operations = vec![
+            operation(OperationType::Buy, 10, 100.0, 1.0, 0),
+            operation(OperationType::Sold, 10, 110.0, 1.0, 30),
+            operation(OperationType::Buy, 10, 100.0, 1.0, 60),
+            operation(OperationType::Sold, 10, 95.0, 1.0, 90),
+        ];
+        let pnl = round_trips(&operations);
+        assert_eq!(pnl.len(), 2);
+        assert_eq!(pnl[0].0, Duration::minutes(30));
+        assert_near(pnl[0].1, 100.0 - 2.0);
+        assert_near(pnl[1].1, -50.0 - 2.0);
+    }
+
+    #[test]
+    fn short_round_trip() {
+        let operations = vec![
+            operation(OperationType::Short, 10, 100.0, 1.0, 0),
+            operation(OperationType::Cover, 10, 90.0, 1.0, 10),
+        ];
+        let pnl = round_trips(&operations);
+        assert_eq!(pnl.len(), 1);
+        assert_near(pnl[0].1, 100.0 - 2.0);
+    }
+
+    #[test]
+    fn partial_closes_share_the_entry() {
+        let operations = vec![
+            operation(OperationType::Buy, 10, 100.0, 2.0, 0),
+            operation(OperationType::Sold, 4, 110.0, 0.5, 10),
+            operation(OperationType::Sold, 6, 90.0, 0.5, 20),
+        ];
+        let pnl = round_trips(&operations);
+        assert_eq!(pnl.len(), 2);
+        // комиссия входа делится по количеству
+        assert_near(pnl[0].1, 40.0 - 0.5 - 0.8);
+        assert_near(pnl[1].1, -60.0 - 0.5 - 1.2);
+        assert_eq!(pnl[1].0, Duration::minutes(20));
+    }
+
+    #[test]
+    fn report_of_operations() {
+        let operations = with_balances(
+            vec![
+                operation(OperationType::Buy, 10, 100.0, 1.0, 0),
+                operation(OperationType::Sold, 10, 110.0, 1.0, 30),
+                operation(OperationType::Short, 10, 110.0, 1.0, 60),
+                operation(OperationType::Cover, 10, 120.0, 1.0, 120),
+            ],
+            1_000.0,
+        );
+        let report = Report::from_operations("test", &operations);
+
+        assert_eq!(report.round_trips, 2);
+        assert_near(report.win_rate, 50.0);
+        assert_near(report.avg_win, 98.0);
+        assert_near(report.avg_loss, -102.0);
+        assert_near(report.start_balance, 1_000.0);
+        assert_near(report.end_balance, 996.0);
+        assert_near(report.total_return, -0.4);
+        assert_near(report.commission, 4.0);
+        // из 120 минут в позиции 90
+        assert_near(report.exposure, 75.0);
+        // пик 1098 после первой сделки, потом 996
+        assert_near(report.max_drawdown, (1_098.0 - 996.0) / 1_098.0 * 100.0);
+    }
+
+    #[test]
+    fn drawdown_of_marked_equity() {
+        let equity = [
+            (time(0), 1_000.0),
+            (time(1), 1_200.0),
+            (time(2), 900.0),
+            (time(3), 1_300.0),
+            (time(4), 1_100.0),
+        ];
+        assert_near(max_drawdown(&equity), 25.0);
+        assert_eq!(max_drawdown(&[]), 0.0);
+    }
+
+    #[test]
+    fn ratios_of_daily_returns() {
+        let day = |days: i64, value: f32| (time(0) + Duration::days(days), value);
+        let equity = [
+            day(0, 100.0),
+            (time(30), 150.0),
+            day(1, 110.0),
+            day(2, 99.0),
+            day(3, 108.9),
+        ];
+        let returns = daily_returns(&equity);
+        // последнее значение дня - его закрытие
+        assert_eq!(returns.len(), 3);
+        assert_near(returns[0], 110.0 / 150.0 - 1.0);
+        assert_near(returns[1], -0.1);
+        assert_near(returns[2], 0.1);
+
+        let returns = [0.01, -0.01, 0.02, 0.0];
+        let avg = 0.005_f32;
+        let deviation = ((0.005_f32.powi(2) * 2.0 + 0.015_f32.powi(2) * 2.0) / 3.0).sqrt();
+        assert_near(
+            ratio(&returns, false),
+            avg / deviation * TRADING_DAYS.sqrt(),
+        );
+        let downside = (0.0001_f32 / 3.0).sqrt();
+        assert_near(ratio(&returns, true), avg / downside * TRADING_DAYS.sqrt());
+        assert_eq!(ratio(&[0.01], false), 0.0);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::models::common::{Candle, Packet, Params};
+use crate::strategy::base::{Signal, Strategy, entry, take_profit};
+use chrono::{NaiveDate, Timelike};
+
+/// Buy a red candle with volume above the yearly average,
+/// or short it when `short` is set
+#[derive(Clone)]
+pub struct AvgVolume {
+    pub avg: i32,
+    pub profit: f32,
+    pub short: bool,
+}
+
+impl AvgVolume {
+    pub fn new(avg: i32) -> Self {
+        Self {
+            avg,
+            profit: 1.5,
+            short: false,
+        }
+    }
+}
+
+impl Strategy for AvgVolume {
+    fn name(&self) -> &'static str {
+        "avg_volume"
+    }
+
+    fn clone_box(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+
+    fn profit(&self) -> f32 {
+        self.profit
+    }
+
+    fn params(&self) -> Params {
+        Params::from([
+            ("profit".to_string(), self.profit),
+            ("avg".to_string(), self.avg as f32),
+            ("short".to_string(), self.short as i32 as f32),
+        ])
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "profit" => self.profit = value,
+            "avg" => self.avg = value as i32,
+            "short" => self.short = value != 0.0,
+            _ => return false,
+        }
+        true
+    }
+
+    fn on_candle(&mut self, candle: &Candle, packet: &Packet) -> Signal {
+        if packet.purchased != 0 {
+            return take_profit(candle, packet);
+        }
+        if candle.volume as i32 > self.avg && candle.open > candle.close {
+            return entry(self.short);
+        }
+        Signal::Hold
+    }
+}
+
+/// Buy a red candle in the afternoon with volume several times above
+/// the average of the previous days, or short it when `short` is set
+#[derive(Clone)]
+pub struct DailyVolume {
+    pub profit: f32,
+    pub short: bool,
+    pub multiplier: f32,
+    pub hour_from: u32,
+    pub hour_to: u32,
+    prev_avg: i32,
+    count: i32,
+    volume: f32,
+}
+
+impl Default for DailyVolume {
+    fn default() -> Self {
+        Self {
+            profit: 1.5,
+            short: false,
+            multiplier: 5.0,
+            hour_from: 13,
+            hour_to: 19,
+            prev_avg: 100,
+            count: 0,
+            volume: 0.0,
+        }
+    }
+}
+
+impl Strategy for DailyVolume {
+    fn name(&self) -> &'static str {
+        "daily_volume"
+    }
+
+    fn clone_box(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+
+    fn profit(&self) -> f32 {
+        self.profit
+    }
+
+    fn params(&self) -> Params {
+        Params::from([
+            ("profit".to_string(), self.profit),
+            ("multiplier".to_string(), self.multiplier),
+            ("hour_from".to_string(), self.hour_from as f32),
+            ("hour_to".to_string(), self.hour_to as f32),
+            ("short".to_string(), self.short as i32 as f32),
+        ])
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "profit" => self.profit = value,
+            "multiplier" => self.multiplier = value,
+            "hour_from" => self.hour_from = value as u32,
+            "hour_to" => self.hour_to = value as u32,
+            "short" => self.short = value != 0.0,
+            _ => return false,
+        }
+        true
+    }
+
+    fn on_day_start(&mut self, _date: NaiveDate, _packet: &Packet) -> Signal {
+        if self.count > 0 {
+            self.prev_avg = (self.volume / self.count as f32) as i32;
+        }
+        Signal::Hold
+    }
+
+    fn on_candle(&mut self, candle: &Candle, packet: &Packet) -> Signal {
+        let buy = candle.begin.hour() >= self.hour_from
+            && candle.begin.hour() < self.hour_to
+            && candle.volume >= self.prev_avg as f32 * self.multiplier
+            && candle.open > candle.close;
+        self.count += 1;
+        self.volume += candle.volume;
+
+        if packet.purchased != 0 {
+            return take_profit(candle, packet);
+        }
+        if buy {
+            return entry(self.short);
+        }
+        Signal::Hold
+    }
+}
+
+/// Wait for a red candle with break volume, then buy on the first flat candle
+#[derive(Clone)]
+pub struct BreakVolume {
+    pub profit: f32,
+    // объём для OZON > 8000
+    pub break_volume: u32,
+    /// no entries from `skip_from` till the end of `skip_to` hour
+    pub skip_from: u32,
+    pub skip_to: u32,
+    volume_ok: bool,
+}
+
+impl Default for BreakVolume {
+    fn default() -> Self {
+        Self {
+            profit: 0.25,
+            break_volume: 1000 * 9,
+            skip_from: 17,
+            skip_to: 18,
+            volume_ok: false,
+        }
+    }
+}
+
+impl Strategy for BreakVolume {
+    fn name(&self) -> &'static str {
+        "break_volume"
+    }
+
+    fn clone_box(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+
+    fn profit(&self) -> f32 {
+        self.profit
+    }
+
+    fn params(&self) -> Params {
+        Params::from([
+            ("profit".to_string(), self.profit),
+            ("break_volume".to_string(), self.break_volume as f32),
+            ("skip_from".to_string(), self.skip_from as f32),
+            ("skip_to".to_string(), self.skip_to as f32),
+        ])
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "profit" => self.profit = value,
+            "break_volume" => self.break_volume = value as u32,
+            "skip_from" => self.skip_from = value as u32,
+            "skip_to" => self.skip_to = value as u32,
+            _ => return false,
+        }
+        true
+    }
+
+    fn on_day_start(&mut self, _date: NaiveDate, _packet: &Packet) -> Signal {
+        self.volume_ok = false;
+        Signal::Hold
+    }
+
+    fn on_candle(&mut self, candle: &Candle, packet: &Packet) -> Signal {
+        if candle.volume as u32 >= self.break_volume {
+            if candle.close > candle.open {
+                self.volume_ok = false;
+            }
+            if candle.close < candle.open {
+                self.volume_ok = true;
+            }
+        }
+
+        let percent = (f32::max(candle.open, candle.close)
+            / (f32::min(candle.open, candle.close) / 100.0))
+            - 100.0;
+        let percent = if candle.open > candle.close {
+            -percent
+        } else {
+            percent
+        };
+
+        if packet.purchased > 0 {
+            return take_profit(candle, packet);
+        }
+        if self.volume_ok
+            && (0.0..=0.001).contains(&percent)
+            && !(self.skip_from..=self.skip_to).contains(&candle.begin.hour())
+        {
+            return Signal::Buy;
+        }
+        Signal::Hold
+    }
+}
@@ -1,9 +1,13 @@
 use crate::db::pg;
 use crate::db::repo;
-use crate::models::common::{Attempt, AvgPeriod, Candle, Frame, Operation, OperationType, Packet};
+use crate::models::common::{Candle, DateRange, ExitRules, FillModel, Frame, Packet, Tick};
 use crate::models::common::{TradeInfo, TradeType};
+use crate::strategy::backtest::{MIN_COUNT, START_BALANCE, backtest, save_result};
+use crate::strategy::registry;
+use crate::strategy::report::Report;
 use chrono::NaiveTime;
-use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use chrono::{NaiveDate, NaiveDateTime};
+use log::{error, info};
 use sqlx::postgres::PgPool;
 use std::time::Duration;
 use uuid::Uuid;
@@ -292,256 +296,235 @@ fn print_trades_result(trades: &Vec<TradesResult>) {
     }
 }
 
-#[allow(dead_code)]
-pub async fn run_strategy(pool: &PgPool) {
-    let begin = NaiveDate::from_ymd_opt(2024, 6, 10)
-        .unwrap()
-        .and_hms_opt(0, 0, 0)
-        .unwrap();
-    // let end = begin + Duration::from_secs(60 * 60 * 24 * 31);
-    let end = NaiveDate::from_ymd_opt(2024, 6, 11)
-        .unwrap()
-        .and_hms_opt(0, 0, 0)
-        .unwrap();
-    // let mut wallet = Wallet { balance: 100_000.0 };
-
-    let packets: Vec<Packet> = vec![
-        Packet::new("OZON", 1, 100_000.0),
-        // Packet::new("LKOH", 1, 100_000.0),
-        // Packet::new("SBER", 10, 100_000.0),
-    ];
-
-    for mut packet in packets {
-        // strategy_1(pool, &mut packet, begin, end).await;
-        // strategy_2(pool, &mut packet, begin, end).await;
-        strategy_3(pool, &mut packet, begin, end).await;
+pub const CANDLES_LIMIT: i32 = 1_000_000;
+
+/// Common settings of backtest runs
+pub struct Settings {
+    pub begin: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub frame: Frame,
+    pub save: bool,
+    pub json: bool,
+    pub exits: ExitRules,
+    /// percent per day of the short position value
+    pub borrow_fee: f32,
+    pub fill: FillModel,
+}
+
+impl Settings {
+    /// Initial packet of a backtest
+    pub fn packet(&self, security: &str) -> Packet {
+        let mut packet = Packet::new(security, MIN_COUNT, START_BALANCE);
+        packet.exits = self.exits;
+        packet.borrow_fee = self.borrow_fee;
+        packet.fill = self.fill;
+        packet
     }
 }
 
-#[allow(dead_code)]
-async fn strategy_3(pool: &PgPool, packet: &mut Packet, begin: NaiveDateTime, end: NaiveDateTime) {
-    let candles = pg::get_candles(pool, &packet.security, begin, end, 200_000, &Frame::M1).await;
-    let mut volume_up: f32 = 0.0;
-    let mut volume_down: f32 = 0.0;
-
-    let attempt = Attempt {
-        id: Uuid::new_v4(),
-        profit: 0.25,
-        commission: 0.04,
-    };
-    pg::add_attempt(pool, &attempt).await;
-    let mut last_operation: Option<Uuid> = None;
-
-    let mut current_date = candles.first().unwrap().begin.date();
-    let candles_skip: Vec<Candle> = candles.clone();
-    // объём для OZON > 8000
-    let break_volume: u32 = 1000 * 9;
-    let mut volume_ok: bool = false;
-    // let mut streak_up: u8 = 0;
-    // let mut streak_down: u8 = 0;
-
-    for (candle, next) in std::iter::zip(candles, candles_skip.iter().skip(1)) {
-        if candle.begin.date() != current_date {
-            current_date = candle.begin.date();
-            volume_up = 0.0;
-            volume_down = 0.0;
-            volume_ok = false;
-            // streak_up = 0;
-            // streak_down = 0;
-            // volume_all = 0.0;
-        }
-        if candle.close > candle.open {
-            volume_up += candle.volume;
-            // streak_up += 1;
-            if candle.volume as u32 >= break_volume {
-                volume_ok = false;
-            }
-        }
-        if candle.close < candle.open {
-            volume_down += candle.volume;
-            // streak_down += 1;
-            if candle.volume as u32 >= break_volume {
-                volume_ok = true;
+pub async fn run_strategy(
+    pool: &PgPool,
+    name: &str,
+    securities: &Vec<String>,
+    settings: &Settings,
+) {
+    let (begin, end) = (settings.begin, settings.end);
+
+    for security in securities {
+        let mut strategy = match registry::create(pool, name, security, begin).await {
+            Some(strategy) => strategy,
+            None => {
+                error!(
+                    "strategy {} not found, available: {}",
+                    name,
+                    registry::STRATEGIES.join(", ")
+                );
+                return;
             }
-        }
-
-        // let diff = volume_up - volume_down;
-        // let red_line = (volume_up - volume_down) / ((volume_up + volume_down) / 100.0);
-        let percent = (f32::max(candle.open, candle.close)
-            / (f32::min(candle.open, candle.close) / 100.0))
-            - 100.0;
-        let percent = if candle.open > candle.close {
-            -1f32 * percent
+        };
+        let candles =
+            pg::get_candles(pool, security, begin, end, CANDLES_LIMIT, &settings.frame).await;
+        let trades = if strategy.uses_trades() {
+            load_trade_info(pool, security, begin, end).await
         } else {
-            percent
+            vec![]
         };
-
-        // println!(
-        //     "time: {}, volume: {}, volume_up: {}, volume_down: {}, profit: {}",
-        //     candle.begin, candle.volume, volume_up, volume_down, packet.profit,
-        // );
-
-        // let sold: bool =
-        //     (candle.close >= packet.profit || red_line < 5.0) && candle.begin.hour() > 12;
-        // let buy: bool = red_line > 5.0
-        //     && candle.close >= candle.open + (candle.open / 100.0) * 0.3
-        //     && candle.begin.hour() < 19;
-
-        let buy: bool = volume_ok
-            && percent >= 0.0
-            && percent <= 0.001
-            && candle.begin.hour() != 17
-            && candle.begin.hour() != 18;
-        let sold: bool = candle.close >= packet.profit;
-
-        if buy {
-            let operation = if buy { "buy" } else { "sold" };
-            println!(
-                "time: {}, operation: {}, profit: {}, balance: {}, percent: {}",
-                candle.begin, operation, packet.profit, packet.balance, percent,
-            );
+        let ticks = load_ticks(pool, security, settings).await;
+
+        let packet = settings.packet(security);
+        let result = backtest(strategy.as_mut(), packet, &candles, &trades, &ticks);
+        info!(
+            "{} => {}, operations: {}, balance: {:.2}",
+            security,
+            strategy.name(),
+            result.operations.len(),
+            result.packets[0].balance
+        );
+        print_report(&Report::from_result(&result), settings.json);
+        if settings.save {
+            save_result(pool, &result, &settings.frame).await;
         }
-
-        last_operation =
-            strategy_logic(pool, packet, next, &attempt, last_operation, sold, buy).await;
     }
 }
 
-#[allow(dead_code)]
-async fn strategy_2(pool: &PgPool, packet: &mut Packet, begin: NaiveDateTime, end: NaiveDateTime) {
-    let mut last_operation: Option<Uuid> = None;
-    let candles = pg::get_candles(pool, &packet.security, begin, end, 200_000, &Frame::M1).await;
-    let attempt = Attempt {
-        id: Uuid::new_v4(),
-        profit: 1.5,
-        commission: 0.04,
-    };
-    pg::add_attempt(pool, &attempt).await;
-
-    let mut prev_avg = 100;
-    let mut current_date = candles.first().unwrap().begin.date();
-    let mut i: i32 = 0;
-    let mut vol: f32 = 0.0;
-
-    for candle in candles {
-        let sold: bool = candle.close >= packet.profit;
-        let buy: bool = candle.begin.hour() >= 13
-            && candle.begin.hour() < 19
-            && candle.volume as i32 >= prev_avg * 5
-            && candle.open > candle.close;
-        last_operation =
-            strategy_logic(pool, packet, &candle, &attempt, last_operation, sold, buy).await;
-        if current_date != candle.begin.date() {
-            current_date = candle.begin.date();
-            prev_avg = (vol / i as f32) as i32;
-        } else {
-            i += 1;
-            vol += candle.volume;
+pub async fn attempt_report(pool: &PgPool, attempt: &str, json: bool) {
+    let id = match Uuid::parse_str(attempt) {
+        Ok(id) => id,
+        Err(e) => {
+            error!("wrong attempt id: {}, {}", attempt, e);
+            return;
         }
+    };
+    let operations = pg::get_operations(pool, id).await;
+    if operations.is_empty() {
+        error!("attempt {} has no operations", attempt);
+        return;
     }
+    print_report(&Report::from_operations(attempt, &operations), json);
 }
 
-#[allow(dead_code)]
-async fn strategy_1(pool: &PgPool, packet: &mut Packet, begin: NaiveDateTime, end: NaiveDateTime) {
-    // находим средний объём торгов за год
-    let avg = pg::get_average_volume(pool, &packet.security, AvgPeriod::Year, begin.year()).await;
-    let mut last_operation: Option<Uuid> = None;
-    let candles = pg::get_candles(pool, &packet.security, begin, end, 200_000, &Frame::M1).await;
-    let attempt = Attempt {
-        id: Uuid::new_v4(),
-        profit: 1.5,
-        commission: 0.04,
-    };
-    pg::add_attempt(pool, &attempt).await;
-
-    for candle in &candles {
-        let sold: bool = candle.close >= packet.profit;
-        let buy: bool = candle.volume as i32 > avg && candle.open > candle.close;
-
-        last_operation =
-            strategy_logic(pool, packet, &candle, &attempt, last_operation, sold, buy).await;
+pub async fn list_attempts(pool: &PgPool, limit: i32) {
+    let attempts = pg::get_attempts(pool, limit).await;
+    let fmt = "%Y-%m-%d";
+    let divider = format!("{:-<150}", "");
+    println!("{divider}");
+    println!(
+        "{:<36} | {:<16} | {:<14} | {:<16} | {:<23} | {:>5} | {:>8} | {:>8} | {:>5}",
+        "attempt",
+        "created",
+        "strategy",
+        "securities",
+        "period",
+        "frame",
+        "return",
+        "drawdown",
+        "trips",
+    );
+    println!("{divider}");
+    for attempt in attempts {
+        let period = match (attempt.begin, attempt.end) {
+            (Some(begin), Some(end)) => format!("{} - {}", begin.format(fmt), end.format(fmt)),
+            _ => String::from("-"),
+        };
+        let report = serde_json::from_value::<Report>(attempt.metrics).ok();
+        println!(
+            "{:<36} | {:<16} | {:<14} | {:<16} | {:<23} | {:>5} | {:>7.2}% | {:>7.2}% | {:>5}",
+            attempt.id,
+            attempt.created_at.format("%Y-%m-%d %H:%M"),
+            attempt.strategy,
+            attempt.securities.join(","),
+            period,
+            attempt.frame,
+            report.as_ref().map(|a| a.total_return).unwrap_or(0.0),
+            report.as_ref().map(|a| a.max_drawdown).unwrap_or(0.0),
+            report.as_ref().map(|a| a.round_trips).unwrap_or(0),
+        );
     }
+    println!("{divider}");
 }
 
-async fn strategy_logic(
-    pool: &PgPool,
-    packet: &mut Packet,
-    candle: &Candle,
-    attempt: &Attempt,
-    prev: Option<Uuid>,
-    sold: bool,
-    buy: bool,
-) -> Option<Uuid> {
-    if packet.purchased > 0 {
-        // выходим
-        if sold {
-            let commission: f32 =
-                ((packet.purchased as f32 * candle.open) / 100.0) * attempt.commission;
-            let op_id = create_operation(
-                pool,
-                attempt,
-                "sold",
-                packet,
-                &commission, //&mut wallet.balance,
-                prev,
-                candle,
-            )
-            .await;
-            packet.purchased = 0;
-            return Some(op_id);
+pub async fn show_attempt(pool: &PgPool, attempt: &str, json: bool) {
+    let id = match Uuid::parse_str(attempt) {
+        Ok(id) => id,
+        Err(e) => {
+            error!("wrong attempt id: {}, {}", attempt, e);
+            return;
         }
-        return prev;
-    }
-    // находим точку входа
-    if buy {
-        let mut count = f32::floor(packet.balance / (candle.open)) as i32;
-        let commission: f32 = ((count as f32 * candle.open) / 100.0) * attempt.commission;
-        while (count as f32 * candle.open) + commission > packet.balance {
-            count -= 1;
+    };
+    let attempt = match pg::get_attempt(pool, id).await {
+        Some(attempt) => attempt,
+        None => {
+            error!("attempt {} not found", id);
+            return;
         }
-        count = (count / packet.min_count) * packet.min_count;
+    };
 
-        if count == 0 {
-            return prev;
+    let report = match serde_json::from_value::<Report>(attempt.metrics.clone()) {
+        Ok(report) => report,
+        Err(_) => {
+            let operations = pg::get_operations(pool, id).await;
+            Report::from_operations(&id.to_string(), &operations)
         }
-        packet.purchased += count;
-        packet.profit = (candle.open / 100.0) * attempt.profit + candle.open;
-        let op_id = create_operation(pool, attempt, "buy", packet, &commission, prev, candle).await;
+    };
 
-        return Some(op_id);
+    let fmt = "%Y-%m-%d %H:%M:%S";
+    if json {
+        let value = serde_json::json!({
+            "attempt": attempt.id.to_string(),
+            "created": attempt.created_at.format(fmt).to_string(),
+            "strategy": attempt.strategy,
+            "securities": attempt.securities,
+            "begin": attempt.begin.map(|a| a.format(fmt).to_string()),
+            "end": attempt.end.map(|a| a.format(fmt).to_string()),
+            "frame": attempt.frame,
+            "version": attempt.version,
+            "commission": attempt.commission,
+            "borrow_fee": attempt.borrow_fee,
+            "fill": attempt.fill,
+            "params": attempt.params,
+            "report": report,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&value).expect("failed to serialize attempt")
+        );
+        return;
     }
-    return prev;
+
+    let params = attempt
+        .params
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    println!("{:<12}{}", "attempt", attempt.id);
+    println!("{:<12}{}", "created", attempt.created_at.format(fmt));
+    println!("{:<12}{}", "strategy", attempt.strategy);
+    println!("{:<12}{}", "securities", attempt.securities.join(","));
+    if let (Some(begin), Some(end)) = (attempt.begin, attempt.end) {
+        println!(
+            "{:<12}{} - {}",
+            "period",
+            begin.format(fmt),
+            end.format(fmt)
+        );
+    }
+    println!("{:<12}{}", "frame", attempt.frame);
+    println!("{:<12}{}", "version", attempt.version);
+    println!("{:<12}{}", "commission", attempt.commission);
+    println!("{:<12}{}", "borrow fee", attempt.borrow_fee);
+    println!("{:<12}{}", "fill", attempt.fill);
+    println!("{:<12}{}", "params", params);
+    report.print();
 }
 
-async fn create_operation(
+pub fn print_report(report: &Report, json: bool) {
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        report.print();
+    }
+}
+
+pub async fn load_trade_info(
     pool: &PgPool,
-    attempt: &Attempt,
-    op_type: &str,
-    packet: &mut Packet,
-    commission: &f32,
-    prev: Option<Uuid>,
-    candle: &Candle,
-) -> Uuid {
-    let id = Uuid::new_v4();
-    let operation_type = OperationType::from(op_type);
-    let mut sum_after: f32 = match operation_type {
-        OperationType::Buy => packet.balance - (packet.purchased as f32 * candle.open),
-        OperationType::Sold => packet.balance + (packet.purchased as f32 * candle.open),
-    };
-    sum_after = sum_after - *commission;
-    let operation = Operation {
-        id,
-        attempt: attempt.id,
-        operation_type,
-        security: packet.security.clone(),
-        count: packet.purchased,
-        price: candle.open,
-        commission: *commission,
-        time_at: candle.begin,
-        sum_before: packet.balance,
-        sum_after,
-    };
-    packet.balance = sum_after;
-    pg::add_operation(pool, &operation, prev).await;
-    return operation.id;
+    security: &str,
+    begin: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Vec<TradeInfo> {
+    let mut result: Vec<TradeInfo> = vec![];
+    for date in DateRange(begin, end) {
+        result.extend(repo::get_trade_info(pool, security, &date.date()).await);
+    }
+    result
+}
+
+/// Recorded trades for the trades fill model, empty otherwise
+pub async fn load_ticks(pool: &PgPool, security: &str, settings: &Settings) -> Vec<Tick> {
+    match settings.fill {
+        FillModel::Open => vec![],
+        FillModel::Trades { .. } => {
+            pg::get_ticks(pool, security, settings.begin, settings.end).await
+        }
+    }
 }
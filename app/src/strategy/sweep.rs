@@ -0,0 +1,207 @@
+use crate::db::pg;
+use crate::models::common::{Candle, Packet, Params, Tick, TradeInfo};
+use crate::strategy::backtest::{BacktestResult, backtest, save_result};
+use crate::strategy::base::Strategy;
+use crate::strategy::registry;
+use crate::strategy::report::Report;
+use crate::strategy::strategy::{CANDLES_LIMIT, Settings, load_ticks, load_trade_info};
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDateTime;
+use log::{error, info};
+use sqlx::postgres::PgPool;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+/// Values of one strategy setting to try
+#[derive(Debug)]
+pub struct ParamRange {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+/// Parses `name=from:to:step` items separated by comma,
+/// `name=value` sets a single value.
+pub fn parse_ranges(spec: &str) -> Result<Vec<ParamRange>> {
+    let mut result = vec![];
+    for item in spec.split(",").map(|a| a.trim()).filter(|a| !a.is_empty()) {
+        let (name, range) = item
+            .split_once("=")
+            .with_context(|| format!("expected name=from:to:step, got: {}", item))?;
+        let bounds = range
+            .split(":")
+            .map(|a| a.trim().parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .with_context(|| format!("wrong range of {}: {}", name, range))?;
+        let values = match bounds[..] {
+            [value] => vec![value],
+            [from, to, step] if step > 0.0 && from <= to => {
+                let count = ((to - from) / step + 1e-4).floor() as usize;
+                (0..=count).map(|i| from + step * i as f32).collect()
+            }
+            _ => bail!("wrong range of {}: {}", name, range),
+        };
+        result.push(ParamRange {
+            name: name.trim().to_string(),
+            values,
+        });
+    }
+    Ok(result)
+}
+
+/// Cartesian product of all ranges
+pub fn combinations(ranges: &[ParamRange]) -> Vec<Params> {
+    let mut result = vec![Params::new()];
+    for range in ranges {
+        result = result
+            .into_iter()
+            .flat_map(|params| {
+                range.values.iter().map(move |value| {
+                    let mut params = params.clone();
+                    params.insert(range.name.clone(), *value);
+                    params
+                })
+            })
+            .collect();
+    }
+    result
+}
+
+pub async fn run_sweep(
+    pool: &PgPool,
+    name: &str,
+    securities: &Vec<String>,
+    spec: &str,
+    settings: &Settings,
+) {
+    let (begin, end) = (settings.begin, settings.end);
+    let ranges = match parse_ranges(spec) {
+        Ok(ranges) => ranges,
+        Err(e) => {
+            error!("{:#}", e);
+            return;
+        }
+    };
+    let combinations = combinations(&ranges);
+
+    for security in securities {
+        let strategies = match build_strategies(pool, name, security, begin, &combinations).await {
+            Some(strategies) => strategies,
+            None => return,
+        };
+        let candles =
+            pg::get_candles(pool, security, begin, end, CANDLES_LIMIT, &settings.frame).await;
+        let trades = if strategies.iter().any(|a| a.uses_trades()) {
+            load_trade_info(pool, security, begin, end).await
+        } else {
+            vec![]
+        };
+        let ticks = load_ticks(pool, security, settings).await;
+
+        let results = sweep(
+            strategies,
+            settings.packet(security),
+            Arc::new(candles),
+            Arc::new(trades),
+            Arc::new(ticks),
+        )
+        .await;
+        for result in results.iter() {
+            save_result(pool, result, &settings.frame).await;
+        }
+        info!(
+            "{} => {}, combinations: {}",
+            security,
+            name,
+            combinations.len()
+        );
+        print_leaderboard(&results, 20);
+    }
+}
+
+/// Creates the strategy once and clones it for every combination of settings
+pub async fn build_strategies(
+    pool: &PgPool,
+    name: &str,
+    security: &str,
+    begin: NaiveDateTime,
+    combinations: &[Params],
+) -> Option<Vec<Box<dyn Strategy>>> {
+    let base = match registry::create(pool, name, security, begin).await {
+        Some(strategy) => strategy,
+        None => {
+            error!(
+                "strategy {} not found, available: {}",
+                name,
+                registry::STRATEGIES.join(", ")
+            );
+            return None;
+        }
+    };
+    let mut result = vec![];
+    for params in combinations {
+        let mut strategy = base.clone_box();
+        for (key, value) in params {
+            if !strategy.set_param(key, *value) {
+                error!("strategy {} has no param {}", name, key);
+                return None;
+            }
+        }
+        result.push(strategy);
+    }
+    Some(result)
+}
+
+/// Runs backtest of every strategy in parallel, each from a copy of `packet`
+pub async fn sweep(
+    strategies: Vec<Box<dyn Strategy>>,
+    packet: Packet,
+    candles: Arc<Vec<Candle>>,
+    trades: Arc<Vec<TradeInfo>>,
+    ticks: Arc<Vec<Tick>>,
+) -> Vec<BacktestResult> {
+    let mut tasks = JoinSet::new();
+    for mut strategy in strategies {
+        let candles = candles.clone();
+        let trades = trades.clone();
+        let ticks = ticks.clone();
+        let packet = packet.clone();
+        tasks
+            .spawn_blocking(move || backtest(strategy.as_mut(), packet, &candles, &trades, &ticks));
+    }
+    tasks.join_all().await
+}
+
+fn print_leaderboard(results: &[BacktestResult], top: usize) {
+    let mut reports = results
+        .iter()
+        .map(|a| (Report::from_result(a), &a.attempt.params))
+        .collect::<Vec<_>>();
+    reports.sort_by(|a, b| b.0.total_return.total_cmp(&a.0.total_return));
+
+    let divider = format!("{:-<130}", "");
+    println!("{divider}");
+    println!(
+        "{:>4} | {:>8} | {:>8} | {:>6} | {:>8} | {:>5} | {:<36} | params",
+        "rank", "return", "drawdown", "sharpe", "win rate", "trips", "attempt",
+    );
+    println!("{divider}");
+    for (i, (report, params)) in reports.iter().take(top).enumerate() {
+        let params = params
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!(
+            "{:>4} | {:>7.2}% | {:>7.2}% | {:>6.2} | {:>7.2}% | {:>5} | {:<36} | {}",
+            i + 1,
+            report.total_return,
+            report.max_drawdown,
+            report.sharpe,
+            report.win_rate,
+            report.round_trips,
+            report.attempt,
+            params,
+        );
+    }
+    println!("{divider}");
+}
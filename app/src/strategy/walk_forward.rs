@@ -0,0 +1,215 @@
+use crate::db::pg;
+use crate::models::common::{Candle, ExitRules, Fold, Params};
+use crate::strategy::backtest::{backtest, save_result};
+use crate::strategy::report::Report;
+use crate::strategy::strategy::{CANDLES_LIMIT, Settings, load_ticks, load_trade_info};
+use crate::strategy::sweep::{build_strategies, combinations, parse_ranges, sweep};
+use log::{error, info};
+use sqlx::postgres::PgPool;
+use std::ops::Range;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const DATE_FMT: &str = "%Y-%m-%d";
+
+/// Settings are tuned on `in_days` trading days and checked on the next
+/// `out_days`, then the window moves forward by `out_days`.
+pub async fn run_walk_forward(
+    pool: &PgPool,
+    name: &str,
+    securities: &Vec<String>,
+    spec: &str,
+    in_days: usize,
+    out_days: usize,
+    settings: &Settings,
+) {
+    let combinations = match parse_ranges(spec) {
+        Ok(ranges) => combinations(&ranges),
+        Err(e) => {
+            error!("{:#}", e);
+            return;
+        }
+    };
+    let run = Uuid::new_v4();
+
+    for security in securities {
+        let candles = pg::get_candles(
+            pool,
+            security,
+            settings.begin,
+            settings.end,
+            CANDLES_LIMIT,
+            &settings.frame,
+        )
+        .await;
+        let days = day_bounds(&candles);
+        let windows = windows(days.len() - 1, in_days, out_days);
+        if windows.is_empty() {
+            error!(
+                "{} => not enough days for walk-forward: {}, need: {}",
+                security,
+                days.len() - 1,
+                in_days + out_days
+            );
+            continue;
+        }
+
+        let uses_trades = match build_strategies(
+            pool,
+            name,
+            security,
+            settings.begin,
+            &combinations[..1],
+        )
+        .await
+        {
+            Some(strategies) => strategies.iter().any(|a| a.uses_trades()),
+            None => return,
+        };
+        let trades = if uses_trades {
+            load_trade_info(pool, security, settings.begin, settings.end).await
+        } else {
+            vec![]
+        };
+        let trades = Arc::new(trades);
+        let ticks = Arc::new(load_ticks(pool, security, settings).await);
+
+        let mut folds: Vec<(Fold, Params)> = vec![];
+        for (i, (in_range, out_range)) in windows.into_iter().enumerate() {
+            let in_candles = &candles[days[in_range.start]..days[in_range.end]];
+            let out_candles = &candles[days[out_range.start]..days[out_range.end]];
+
+            let strategies =
+                match build_strategies(pool, name, security, in_candles[0].begin, &combinations)
+                    .await
+                {
+                    Some(strategies) => strategies,
+                    None => return,
+                };
+            let results = sweep(
+                strategies,
+                settings.packet(security),
+                Arc::new(in_candles.to_vec()),
+                trades.clone(),
+                ticks.clone(),
+            )
+            .await;
+            let best = match results
+                .iter()
+                .map(|a| (Report::from_result(a), &a.attempt.params))
+                .max_by(|a, b| a.0.total_return.total_cmp(&b.0.total_return))
+            {
+                Some(best) => best,
+                None => continue,
+            };
+
+            // правила выхода общие для всех, у стратегии таких настроек нет
+            let params: Params = best
+                .1
+                .iter()
+                .filter(|(key, _)| !ExitRules::PARAMS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), *value))
+                .collect();
+            let mut strategy = match build_strategies(
+                pool,
+                name,
+                security,
+                out_candles[0].begin,
+                std::slice::from_ref(&params),
+            )
+            .await
+            {
+                Some(mut strategies) => strategies.remove(0),
+                None => return,
+            };
+            let packet = settings.packet(security);
+            let result = backtest(strategy.as_mut(), packet, out_candles, &trades, &ticks);
+            let report = Report::from_result(&result);
+            save_result(pool, &result, &settings.frame).await;
+
+            let fold = Fold {
+                id: Uuid::new_v4(),
+                run,
+                attempt: result.attempt.id,
+                fold: i as i32 + 1,
+                security: security.to_string(),
+                in_begin: in_candles[0].begin,
+                in_end: in_candles[in_candles.len() - 1].end,
+                out_begin: out_candles[0].begin,
+                out_end: out_candles[out_candles.len() - 1].end,
+                in_return: best.0.total_return,
+                out_return: report.total_return,
+                out_drawdown: report.max_drawdown,
+                out_sharpe: report.sharpe,
+            };
+            pg::add_fold(pool, &fold).await;
+            folds.push((fold, params));
+        }
+
+        info!("{} => {}, walk-forward run: {}", security, name, run);
+        print_folds(&folds);
+    }
+}
+
+/// Index of the first candle of every day, plus candles length
+fn day_bounds(candles: &[Candle]) -> Vec<usize> {
+    let mut result = vec![];
+    for (i, candle) in candles.iter().enumerate() {
+        if i == 0 || candles[i - 1].begin.date() != candle.begin.date() {
+            result.push(i);
+        }
+    }
+    result.push(candles.len());
+    result
+}
+
+/// Rolling in-sample and out-of-sample ranges of day indexes
+fn windows(days: usize, in_days: usize, out_days: usize) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut result = vec![];
+    if in_days == 0 || out_days == 0 {
+        return result;
+    }
+    let mut start = 0;
+    while start + in_days + out_days <= days {
+        result.push((
+            start..start + in_days,
+            start + in_days..start + in_days + out_days,
+        ));
+        start += out_days;
+    }
+    result
+}
+
+fn print_folds(folds: &[(Fold, Params)]) {
+    let divider = format!("{:-<120}", "");
+    println!("{divider}");
+    println!(
+        "{:>4} | {:<23} | {:<23} | {:>9} | {:>9} | {:>8} | {:>6} | params",
+        "fold", "in-sample", "out-of-sample", "in", "out", "drawdown", "sharpe",
+    );
+    println!("{divider}");
+    let mut total: f32 = 1.0;
+    for (fold, params) in folds {
+        let params = params
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!(
+            "{:>4} | {} - {} | {} - {} | {:>8.2}% | {:>8.2}% | {:>7.2}% | {:>6.2} | {}",
+            fold.fold,
+            fold.in_begin.format(DATE_FMT),
+            fold.in_end.format(DATE_FMT),
+            fold.out_begin.format(DATE_FMT),
+            fold.out_end.format(DATE_FMT),
+            fold.in_return,
+            fold.out_return,
+            fold.out_drawdown,
+            fold.out_sharpe,
+            params,
+        );
+        total *= 1.0 + fold.out_return / 100.0;
+    }
+    println!("{divider}");
+    println!("out-of-sample return: {:.2}%", (total - 1.0) * 100.0);
+}
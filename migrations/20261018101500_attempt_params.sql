@@ -0,0 +1 @@
+alter table public.attempts add column params jsonb not null default '{}'::jsonb;
@@ -0,0 +1,16 @@
+create table if not exists folds
+(
+    id uuid primary key not null default uuid_generate_v4(),
+    run_id uuid not null,
+    attempt_id uuid not null references public.attempts(id) on delete cascade,
+    fold integer not null default 0,
+    security_id uuid not null references public.securities(id) on delete cascade,
+    in_begin timestamp without time zone not null,
+    in_end timestamp without time zone not null,
+    out_begin timestamp without time zone not null,
+    out_end timestamp without time zone not null,
+    in_return decimal not null default 0,
+    out_return decimal not null default 0,
+    out_drawdown decimal not null default 0,
+    out_sharpe decimal not null default 0
+);
@@ -0,0 +1,7 @@
+alter table public.attempts add column strategy varchar(255) not null default '';
+alter table public.attempts add column securities text[] not null default '{}';
+alter table public.attempts add column begin_t timestamp without time zone;
+alter table public.attempts add column end_t timestamp without time zone;
+alter table public.attempts add column frame varchar(16) not null default '';
+alter table public.attempts add column version varchar(255) not null default '';
+alter table public.attempts add column metrics jsonb not null default '{}'::jsonb;
@@ -0,0 +1 @@
+alter table public.attempts add column borrow_fee decimal not null default 0;
@@ -0,0 +1 @@
+alter table public.attempts add column fill varchar(255) not null default 'open';
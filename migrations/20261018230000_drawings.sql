@@ -0,0 +1,12 @@
+create table if not exists drawings
+(
+    id uuid primary key not null default uuid_generate_v4(),
+    security_id uuid not null references public.securities(id) on delete cascade,
+    kind varchar(255) not null check (kind in ('level', 'line', 'rect', 'note')),
+    begin_t timestamp without time zone not null,
+    begin_price decimal not null,
+    end_t timestamp without time zone not null,
+    end_price decimal not null,
+    text text not null default '',
+    created_at timestamp without time zone not null default now()
+);
@@ -0,0 +1,179 @@
+use crate::layout::Cell;
+use crate::operations::draw_operations;
+use crate::overlays::{Indicators, Overlays, draw_overlays, draw_panel};
+use crate::viewport::Viewport;
+use crate::{DrawCoords, draw_axis, draw_dropdown, draw_graphs, draw_trades};
+use app::models::common::{Candle, Frame, Operation, TradeView};
+use raylib::prelude::*;
+
+pub const FRAMES: &str = "m1;m15;h1;d1";
+
+/// One chart of the split view with its own security and frame
+pub struct Chart {
+    pub security: String,
+    pub security_active: i32,
+    pub security_edit: bool,
+    pub frame_active: i32,
+    pub frame_edit: bool,
+    pub viewport: Viewport,
+    pub candles: Vec<Candle>,
+    pub coords: DrawCoords,
+    pub trades: Vec<TradeView>,
+    /// indicators of the whole history of the viewport
+    series: Indicators,
+    /// indicators of the visible candles
+    pub indicators: Indicators,
+    pub current_candle: Candle,
+}
+
+impl Chart {
+    /// Chart over a viewport from the loader, never empty: the loader answers
+    /// with an error when `Viewport::load` finds no candles
+    pub fn new(
+        security_active: i32,
+        security: String,
+        frame_active: i32,
+        viewport: Viewport,
+        cell: &Cell,
+    ) -> Self {
+        let (candles, trades, coords) = viewport.view(&cell.chart);
+        let series = Indicators::new(&viewport.history, &viewport.trades);
+        let indicators = series.slice(viewport.visible(&cell.chart), &trades);
+        let current_candle = viewport.history[0].clone();
+
+        Self {
+            security,
+            security_active,
+            security_edit: false,
+            frame_active,
+            frame_edit: false,
+            viewport,
+            candles,
+            coords,
+            trades,
+            series,
+            indicators,
+            current_candle,
+        }
+    }
+
+    pub fn frame(&self) -> Frame {
+        frame(self.frame_active)
+    }
+
+    /// One of the dropdowns is open
+    pub fn is_editing(&self) -> bool {
+        self.security_edit || self.frame_edit
+    }
+
+    /// Visible candles and trades after pan, zoom or resize
+    pub fn update_view(&mut self, cell: &Cell) {
+        (self.candles, self.trades, self.coords) = self.viewport.view(&cell.chart);
+        let range = self.viewport.visible(&cell.chart);
+        self.indicators = self.series.slice(range, &self.trades);
+    }
+
+    /// Indicators of the history after a chunk is added, then the view
+    pub fn update_history(&mut self, cell: &Cell) {
+        self.series = Indicators::new(&self.viewport.history, &self.viewport.trades);
+        self.update_view(cell);
+    }
+
+    /// Candles and trades loaded after a change of the security, frame or range
+    pub fn set(
+        &mut self,
+        security_active: i32,
+        security: String,
+        frame_active: i32,
+        viewport: Viewport,
+        cell: &Cell,
+    ) {
+        self.security = security;
+        self.security_active = security_active;
+        self.frame_active = frame_active;
+        self.viewport = viewport;
+        self.update_history(cell);
+    }
+
+    pub fn draw(
+        &mut self,
+        d: &mut RaylibDrawHandle,
+        font: &Font,
+        cell: &Cell,
+        overlays: &Overlays,
+        operations: &[Operation],
+    ) {
+        let frame = self.frame();
+
+        // candles
+        draw_axis(d, font, &self.coords);
+        draw_graphs(
+            d,
+            &self.coords,
+            &mut self.candles,
+            &frame,
+            font,
+            &self.current_candle,
+        );
+        draw_overlays(d, &self.coords, &self.indicators, overlays);
+        draw_operations(
+            d,
+            font,
+            &self.coords,
+            &self.candles,
+            operations,
+            &self.security,
+        );
+
+        // trades
+        draw_trades(d, font, &self.trades, &self.coords, &cell.trades, &frame);
+        draw_panel(
+            d,
+            font,
+            &self.coords,
+            &cell.panel,
+            &self.indicators,
+            overlays,
+        );
+    }
+
+    /// Security and frame dropdowns over the chart, returns the selected ones
+    /// when one of them changed, the chart keeps the current until `set`
+    pub fn draw_header(
+        &mut self,
+        d: &mut RaylibDrawHandle,
+        securities: &str,
+        cell: &Cell,
+    ) -> Option<(i32, i32)> {
+        let mut security_active = self.security_active;
+        if draw_dropdown(
+            d,
+            securities,
+            &mut security_active,
+            &mut self.security_edit,
+            cell.securities,
+            false,
+        ) {
+            self.security_edit = !self.security_edit;
+        }
+
+        let mut frame_active = self.frame_active;
+        if draw_dropdown(
+            d,
+            FRAMES,
+            &mut frame_active,
+            &mut self.frame_edit,
+            cell.frames,
+            false,
+        ) {
+            self.frame_edit = !self.frame_edit;
+        }
+
+        let selected = (security_active, frame_active);
+        (selected != (self.security_active, self.frame_active)).then_some(selected)
+    }
+}
+
+pub fn frame(frame_active: i32) -> Frame {
+    Frame::from(FRAMES.split(";").nth(frame_active as usize).unwrap_or("m1"))
+}
@@ -0,0 +1,349 @@
+use crate::chart::FRAMES;
+use crate::layout::MAX_CHARTS;
+use crate::range::PRESETS;
+use crate::{BACKGROUND_COLOR, DATE_FMT};
+use chrono::NaiveDate;
+use raylib::prelude::*;
+
+const FONT_SIZE: f32 = 15.0;
+const LINE_H: f32 = 20.0;
+const PADDING: f32 = 8.0;
+const PALETTE_W: f32 = 420.0;
+const TOP: f32 = 60.0;
+/// Matches shown in the palette
+const MAX_MATCHES: usize = 12;
+
+/// Hotkeys listed in the help overlay
+const BINDINGS: [(&str, &str); 18] = [
+    ("UP / DOWN", "previous / next security"),
+    ("PAGE UP / PAGE DOWN", "previous / next trading date"),
+    ("1 2 3 4", "frame m1 / m15 / h1 / d1"),
+    ("G", "jump to date"),
+    ("R", "replay of the range"),
+    ("SPACE / N", "play or pause / step of the replay"),
+    ("B / S", "paper buy / sell during the replay"),
+    ("F5", "refresh the attempts list"),
+    ("CTRL+P", "command palette"),
+    ("CTRL+S", "snapshot of the active chart to png"),
+    ("CTRL+E", "export of the active chart to svg"),
+    ("F1", "this help"),
+    ("ESC", "close palette or help, exit"),
+    ("LEFT / RIGHT", "scroll, faster with shift"),
+    ("WHEEL / DRAG", "zoom / pan"),
+    ("CLICK", "select chart, candle or drawing"),
+    ("DELETE", "remove the selected drawing"),
+    ("ENTER", "run the selected command"),
+];
+
+/// Actions of the palette besides securities, frames, splits and presets
+const ACTIONS: [(&str, Command); 16] = [
+    ("next security", Command::NextSecurity),
+    ("previous security", Command::PrevSecurity),
+    ("next date", Command::NextDate),
+    ("previous date", Command::PrevDate),
+    ("jump to date", Command::JumpToDate),
+    ("sync cursor", Command::SyncCursor),
+    ("snapshot png", Command::SnapshotPng),
+    ("export svg", Command::ExportSvg),
+    ("replay", Command::Replay),
+    ("replay play / pause", Command::ReplayPlay),
+    ("replay step", Command::ReplayStep),
+    ("paper buy", Command::PaperBuy),
+    ("paper sell", Command::PaperSell),
+    ("save paper trades", Command::PaperSave),
+    ("refresh attempts", Command::RefreshAttempts),
+    ("help", Command::Help),
+];
+
+/// Action of a hotkey or of the palette, security and frame ones apply to the active chart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Security(i32),
+    NextSecurity,
+    PrevSecurity,
+    Frame(i32),
+    NextDate,
+    PrevDate,
+    Date(NaiveDate),
+    Preset(usize),
+    Split(usize),
+    SyncCursor,
+    SnapshotPng,
+    ExportSvg,
+    Replay,
+    ReplayPlay,
+    ReplayStep,
+    PaperBuy,
+    PaperSell,
+    PaperSave,
+    RefreshAttempts,
+    JumpToDate,
+    Palette,
+    Help,
+}
+
+/// Text input over the charts: the palette or the jump-to-date one
+struct Input {
+    query: String,
+    selected: usize,
+    jump: bool,
+    error: bool,
+}
+
+/// Hotkeys, the command palette and the help overlay
+#[derive(Default)]
+pub struct Commands {
+    input: Option<Input>,
+    help: bool,
+}
+
+impl Commands {
+    /// The palette or the jump-to-date input takes the keyboard
+    pub fn is_open(&self) -> bool {
+        self.input.is_some()
+    }
+
+    /// Esc closes an overlay instead of the window
+    pub fn has_overlay(&self) -> bool {
+        self.input.is_some() || self.help
+    }
+
+    /// Hotkeys or keys of the open input, returns the command to run
+    pub fn handle_input(&mut self, d: &mut RaylibDrawHandle, secs: &[&str]) -> Option<Command> {
+        let command = match self.input {
+            Some(_) => self.handle_keys(d, secs)?,
+            None => {
+                if self.help && d.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+                    self.help = false;
+                }
+                hotkey(d)?
+            }
+        };
+        match command {
+            Command::Palette => self.open(d, false),
+            Command::JumpToDate => self.open(d, true),
+            Command::Help => self.help = !self.help,
+            _ => return Some(command),
+        }
+        None
+    }
+
+    fn open(&mut self, d: &mut RaylibDrawHandle, jump: bool) {
+        // буква хоткея не должна попасть в поле ввода
+        while d.get_char_pressed().is_some() {}
+        self.input = Some(Input {
+            query: String::new(),
+            selected: 0,
+            jump,
+            error: false,
+        });
+    }
+
+    fn handle_keys(&mut self, d: &mut RaylibDrawHandle, secs: &[&str]) -> Option<Command> {
+        let input = self.input.as_mut()?;
+        while let Some(c) = d.get_char_pressed() {
+            input.query.push(c);
+            input.selected = 0;
+            input.error = false;
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            input.query.pop();
+            input.selected = 0;
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            self.input = None;
+            return None;
+        }
+
+        if input.jump {
+            if d.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                match NaiveDate::parse_from_str(input.query.trim(), DATE_FMT) {
+                    Ok(date) => {
+                        self.input = None;
+                        return Some(Command::Date(date));
+                    }
+                    Err(_) => input.error = true,
+                }
+            }
+            return None;
+        }
+
+        let matches = matches(&input.query, secs);
+        if d.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            input.selected = (input.selected + 1).min(matches.len().saturating_sub(1));
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_UP) {
+            input.selected = input.selected.saturating_sub(1);
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            let command = matches.get(input.selected).map(|a| a.1);
+            self.input = None;
+            return command;
+        }
+        None
+    }
+
+    /// The open input with its matches and the help overlay, centered over the window
+    pub fn draw(&self, d: &mut RaylibDrawHandle, font: &Font, width: f32, secs: &[&str]) {
+        if self.help {
+            let lines = BINDINGS
+                .iter()
+                .map(|(key, action)| format!("{:<22}{}", key, action))
+                .collect::<Vec<_>>();
+            let box_w = lines
+                .iter()
+                .map(|a| font.measure_text(a, FONT_SIZE, 0.0).x)
+                .fold(0.0, f32::max)
+                + PADDING * 2.0;
+            let position = Vector2::new((width - box_w) / 2.0, TOP);
+            draw_lines(d, font, &lines, position, box_w, None);
+        }
+
+        if let Some(input) = &self.input {
+            let position = Vector2::new((width - PALETTE_W) / 2.0, TOP);
+            if input.jump {
+                let mut lines = vec![format!("DATE > {}_", input.query)];
+                if input.error {
+                    lines.push(String::from("expected YYYY-MM-DD"));
+                }
+                draw_lines(d, font, &lines, position, PALETTE_W, None);
+            } else {
+                let mut lines = vec![format!("> {}_", input.query)];
+                lines.extend(matches(&input.query, secs).into_iter().map(|a| a.0));
+                draw_lines(
+                    d,
+                    font,
+                    &lines,
+                    position,
+                    PALETTE_W,
+                    Some(input.selected + 1),
+                );
+            }
+        }
+    }
+}
+
+fn hotkey(d: &RaylibDrawHandle) -> Option<Command> {
+    let ctrl = d.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+        || d.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+    if ctrl {
+        let keys = [
+            (KeyboardKey::KEY_P, Command::Palette),
+            (KeyboardKey::KEY_S, Command::SnapshotPng),
+            (KeyboardKey::KEY_E, Command::ExportSvg),
+        ];
+        return keys
+            .iter()
+            .find(|(key, _)| d.is_key_pressed(*key))
+            .map(|a| a.1);
+    }
+    let keys = [
+        (KeyboardKey::KEY_DOWN, Command::NextSecurity),
+        (KeyboardKey::KEY_UP, Command::PrevSecurity),
+        (KeyboardKey::KEY_PAGE_DOWN, Command::NextDate),
+        (KeyboardKey::KEY_PAGE_UP, Command::PrevDate),
+        (KeyboardKey::KEY_ONE, Command::Frame(0)),
+        (KeyboardKey::KEY_TWO, Command::Frame(1)),
+        (KeyboardKey::KEY_THREE, Command::Frame(2)),
+        (KeyboardKey::KEY_FOUR, Command::Frame(3)),
+        (KeyboardKey::KEY_G, Command::JumpToDate),
+        (KeyboardKey::KEY_R, Command::Replay),
+        (KeyboardKey::KEY_SPACE, Command::ReplayPlay),
+        (KeyboardKey::KEY_N, Command::ReplayStep),
+        (KeyboardKey::KEY_B, Command::PaperBuy),
+        (KeyboardKey::KEY_S, Command::PaperSell),
+        (KeyboardKey::KEY_F1, Command::Help),
+        (KeyboardKey::KEY_F5, Command::RefreshAttempts),
+    ];
+    keys.iter()
+        .find(|(key, _)| d.is_key_pressed(*key))
+        .map(|a| a.1)
+}
+
+/// Entries of the palette that fuzzy match `query`, best first
+fn matches(query: &str, secs: &[&str]) -> Vec<(String, Command)> {
+    let mut entries = secs
+        .iter()
+        .enumerate()
+        .map(|(i, a)| (a.to_string(), Command::Security(i as i32)))
+        .collect::<Vec<_>>();
+    for (i, frame) in FRAMES.split(";").enumerate() {
+        entries.push((format!("frame {frame}"), Command::Frame(i as i32)));
+    }
+    for (i, preset) in PRESETS.iter().enumerate() {
+        entries.push((
+            format!("range {}", preset.to_lowercase()),
+            Command::Preset(i),
+        ));
+    }
+    for i in 1..=MAX_CHARTS {
+        entries.push((format!("split {i}"), Command::Split(i)));
+    }
+    entries.extend(ACTIONS.iter().map(|(label, a)| (label.to_string(), *a)));
+
+    let mut matches = entries
+        .into_iter()
+        .filter_map(|(label, command)| Some((fuzzy(query, &label)?, label, command)))
+        .collect::<Vec<_>>();
+    matches.sort_by(|a, b| b.0.cmp(&a.0));
+    matches
+        .into_iter()
+        .take(MAX_MATCHES)
+        .map(|(_, label, command)| (label, command))
+        .collect()
+}
+
+/// Score of `label` having the letters of `query` in this order, higher for
+/// consecutive letters and a match at the start, `None` if it doesn't have them
+fn fuzzy(query: &str, label: &str) -> Option<i32> {
+    let label = label.to_lowercase();
+    let mut chars = label.chars().enumerate();
+    let mut score = 0;
+    let mut last = None;
+    for q in query.to_lowercase().chars().filter(|a| !a.is_whitespace()) {
+        let (i, _) = chars.find(|(_, c)| *c == q)?;
+        score += match last {
+            Some(last) if i == last + 1 => 3,
+            _ => 1,
+        };
+        if i == 0 {
+            score += 2;
+        }
+        last = Some(i);
+    }
+    Some(score)
+}
+
+/// Lines of text on a filled box, the `selected` one is highlighted
+fn draw_lines(
+    d: &mut RaylibDrawHandle,
+    font: &Font,
+    lines: &[String],
+    position: Vector2,
+    width: f32,
+    selected: Option<usize>,
+) {
+    let bounds = Rectangle::new(
+        position.x,
+        position.y,
+        width,
+        lines.len() as f32 * LINE_H + PADDING * 2.0,
+    );
+    d.draw_rectangle_rec(bounds, BACKGROUND_COLOR.alpha(0.95));
+    d.draw_rectangle_lines_ex(bounds, 1.0, Color::GRAY);
+    for (i, line) in lines.iter().enumerate() {
+        let y = position.y + PADDING + i as f32 * LINE_H;
+        if selected == Some(i) {
+            let line_bounds = Rectangle::new(position.x + 1.0, y - 2.0, width - 2.0, LINE_H);
+            d.draw_rectangle_rec(line_bounds, Color::SKYBLUE.alpha(0.3));
+        }
+        d.draw_text_ex(
+            font,
+            line,
+            Vector2::new(position.x + PADDING, y),
+            FONT_SIZE,
+            0.0,
+            Color::WHEAT,
+        );
+    }
+}
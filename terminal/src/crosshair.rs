@@ -0,0 +1,165 @@
+use crate::chart::Chart;
+use crate::layout::{Cell, Layout};
+use crate::{BACKGROUND_COLOR, DATE_FMT, candle_x, convert_value_y, trades_range};
+use app::models::common::Frame;
+use chrono::NaiveDateTime;
+use raylib::prelude::*;
+
+const FONT_SIZE: f32 = 15.0;
+const LINE_H: f32 = 17.0;
+const PADDING: f32 = 4.0;
+
+/// Crosshair over the candle and trades panels: price or quantity on the y-axis,
+/// time on the x-axis and a tooltip of the candle under the cursor,
+/// returns the time of that candle
+pub fn draw_crosshair(
+    d: &mut RaylibDrawHandle,
+    font: &Font,
+    layout: &Layout,
+    cell: &Cell,
+    chart: &Chart,
+) -> Option<NaiveDateTime> {
+    let (coords, candles, trades) = (&chart.coords, &chart.candles, &chart.trades);
+    let frame = &chart.frame();
+    let mouse = d.get_mouse_position();
+    let trades_start = cell.trades.y;
+    let trades_end = cell.trades.y + cell.trades.height;
+    let in_candles = mouse.y >= coords.start_pos.y && mouse.y <= coords.end_pos.y;
+    let in_trades = mouse.y >= trades_start && mouse.y <= trades_end;
+    if mouse.x < coords.start_pos.x || mouse.x > coords.end_pos.x || !(in_candles || in_trades) {
+        return None;
+    }
+
+    // lines
+    let color = Color::WHEAT.alpha(0.5);
+    for (start, end) in [
+        (coords.start_pos.y, coords.end_pos.y),
+        (trades_start, trades_end),
+    ] {
+        d.draw_line_v(
+            Vector2::new(mouse.x, start),
+            Vector2::new(mouse.x, end),
+            color,
+        );
+    }
+    d.draw_line_v(
+        Vector2::new(coords.start_pos.x, mouse.y),
+        Vector2::new(coords.end_pos.x, mouse.y),
+        color,
+    );
+
+    // y-axis
+    let value = if in_candles {
+        let price = convert_value_y(coords.start_pos.y, coords.step_y, coords.max_y, mouse.y);
+        Some(format!("{:.2}", price))
+    } else {
+        let (min_y, max_y) = trades_range(trades);
+        (max_y > min_y).then(|| {
+            let step_y = (trades_end - trades_start) / (max_y - min_y) as f32;
+            let quantity = convert_value_y(trades_start, step_y, max_y as f32, mouse.y);
+            format!("{:.0}", quantity)
+        })
+    };
+    if let Some(value) = value {
+        let size = font.measure_text(&value, FONT_SIZE, 0.0);
+        let position = Vector2::new(
+            coords.start_pos.x - size.x - PADDING * 3.0,
+            mouse.y - size.y / 2.0,
+        );
+        draw_label(d, font, &[value], position);
+    }
+
+    // x-axis
+    let i = ((mouse.x - coords.start_pos.x) / coords.candle_w).floor() as i64 - 1;
+    let candle = usize::try_from(i).ok().and_then(|i| candles.get(i))?;
+    let time = candle.begin.format(time_format(frame)).to_string();
+    let size = font.measure_text(&time, FONT_SIZE, 0.0);
+    for y in [coords.end_pos.y, trades_end] {
+        let position = Vector2::new(mouse.x - size.x / 2.0, y + PADDING);
+        draw_label(d, font, &[time.clone()], position);
+    }
+
+    // tooltip
+    let trade = trades.get(i as usize);
+    let mut lines = vec![
+        time,
+        format!("O {:.2}  H {:.2}", candle.open, candle.high),
+        format!("L {:.2}  C {:.2}", candle.low, candle.close),
+        format!("VOL {:.0}", candle.volume),
+        format!("VAL {:.0}", candle.value),
+    ];
+    if let Some(trade) = trade {
+        lines.push(format!(
+            "BUY {}  SELL {}",
+            trade.quantity_buy, trade.quantity_sell
+        ));
+    }
+    let width = lines
+        .iter()
+        .map(|a| font.measure_text(a, FONT_SIZE, 0.0).x)
+        .fold(0.0, f32::max);
+    let height = lines.len() as f32 * LINE_H;
+    let mut position = Vector2::new(mouse.x + 16.0, mouse.y + 16.0);
+    if position.x + width + PADDING * 2.0 > layout.settings.width as f32 {
+        position.x = mouse.x - width - PADDING * 2.0 - 16.0;
+    }
+    if position.y + height + PADDING * 2.0 > layout.settings.height as f32 {
+        position.y = mouse.y - height - PADDING * 2.0 - 16.0;
+    }
+    draw_label(d, font, &lines, position);
+
+    Some(candle.begin)
+}
+
+/// Line through the candle of another chart that contains `time`
+pub fn draw_time_cursor(d: &mut RaylibDrawHandle, cell: &Cell, chart: &Chart, time: NaiveDateTime) {
+    let i = chart.candles.partition_point(|a| a.begin <= time);
+    if i == 0 || (i == chart.candles.len() && time > chart.candles[i - 1].end) {
+        return;
+    }
+    let x = candle_x(&chart.coords, i - 1);
+    let color = Color::SKYBLUE.alpha(0.6);
+    for area in [cell.chart, cell.trades] {
+        d.draw_line_v(
+            Vector2::new(x, area.y),
+            Vector2::new(x, area.y + area.height),
+            color,
+        );
+    }
+}
+
+/// Lines of text on a filled box, `position` is the top-left corner of the box
+fn draw_label(d: &mut RaylibDrawHandle, font: &Font, lines: &[String], position: Vector2) {
+    let width = lines
+        .iter()
+        .map(|a| font.measure_text(a, FONT_SIZE, 0.0).x)
+        .fold(0.0, f32::max);
+    let bounds = Rectangle::new(
+        position.x,
+        position.y,
+        width + PADDING * 2.0,
+        lines.len() as f32 * LINE_H + PADDING * 2.0,
+    );
+    d.draw_rectangle_rec(bounds, BACKGROUND_COLOR.alpha(0.9));
+    d.draw_rectangle_lines_ex(bounds, 1.0, Color::GRAY);
+    for (i, line) in lines.iter().enumerate() {
+        d.draw_text_ex(
+            font,
+            line,
+            Vector2::new(
+                position.x + PADDING,
+                position.y + PADDING + i as f32 * LINE_H,
+            ),
+            FONT_SIZE,
+            0.0,
+            Color::WHEAT,
+        );
+    }
+}
+
+fn time_format(frame: &Frame) -> &'static str {
+    match frame {
+        Frame::D1 => DATE_FMT,
+        _ => "%Y-%m-%d %H:%M",
+    }
+}
@@ -0,0 +1,388 @@
+use crate::chart::Chart;
+use crate::layout::Cell;
+use crate::loader::{Loader, Request, Target};
+use crate::{BACKGROUND_COLOR, convert_coords_y, convert_value_y};
+use app::models::common::{Drawing, DrawingKind};
+use chrono::NaiveDateTime;
+use raylib::prelude::*;
+use sqlx::types::Uuid;
+use std::collections::HashMap;
+
+/// Tools of the toggle group in the sidebar, the first one selects and moves drawings
+pub const TOOLS: &str = "NONE;LEVEL;LINE;RECT;NOTE";
+/// Distance to an anchor or a level in pixels to grab it
+const HIT_DISTANCE: f32 = 6.0;
+const HANDLE_SIZE: f32 = 6.0;
+const FONT_SIZE: f32 = 15.0;
+const PADDING: f32 = 4.0;
+const COLOR: Color = Color::GOLD;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Anchor {
+    Begin,
+    End,
+}
+
+/// Drawing moved with the mouse on the chart with index `chart`
+struct Drag {
+    chart: usize,
+    id: Uuid,
+    anchor: Anchor,
+}
+
+/// Drawings of the charts, loaded per security, and the tool that creates new ones
+#[derive(Default)]
+pub struct Drawings {
+    pub tool: i32,
+    by_security: HashMap<String, Vec<Drawing>>,
+    selected: Option<Uuid>,
+    drag: Option<Drag>,
+    /// note whose text is typed
+    typing: Option<Uuid>,
+}
+
+impl Drawings {
+    /// Requests the drawings of the securities of the charts that are not loaded yet
+    pub fn load(&mut self, loader: &mut Loader, charts: &[Chart]) {
+        for chart in charts {
+            if !self.by_security.contains_key(&chart.security) {
+                // пустой список, чтобы не запрашивать повторно
+                self.by_security.insert(chart.security.clone(), vec![]);
+                loader.send(
+                    Target::Drawings(chart.security_active),
+                    Request::Drawings(chart.security.clone()),
+                );
+            }
+        }
+    }
+
+    /// Drawings from the database, before the ones created while they were loading
+    pub fn add(&mut self, security: String, drawings: Vec<Drawing>) {
+        self.by_security
+            .entry(security)
+            .or_default()
+            .splice(0..0, drawings);
+    }
+
+    pub fn of(&self, security: &str) -> &[Drawing] {
+        self.by_security.get(security).map_or(&[], |a| a.as_slice())
+    }
+
+    /// The text of a note takes the keyboard
+    pub fn is_typing(&self) -> bool {
+        self.typing.is_some()
+    }
+
+    /// Text of the typed note, Delete removes the selected drawing
+    pub fn handle_keys(&mut self, d: &mut RaylibDrawHandle, loader: &mut Loader) {
+        if let Some(id) = self.typing {
+            let Some(note) = self.find_mut(id) else {
+                self.typing = None;
+                return;
+            };
+            while let Some(c) = d.get_char_pressed() {
+                note.text.push(c);
+            }
+            if d.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+                note.text.pop();
+            }
+            if d.is_key_pressed(KeyboardKey::KEY_ENTER) || d.is_key_pressed(KeyboardKey::KEY_ESCAPE)
+            {
+                let note = note.clone();
+                self.typing = None;
+                if note.text.trim().is_empty() {
+                    self.remove(loader, id);
+                } else {
+                    save(loader, note);
+                }
+            }
+            return;
+        }
+
+        if d.is_key_pressed(KeyboardKey::KEY_DELETE)
+            && let Some(id) = self.selected
+        {
+            self.remove(loader, id);
+        }
+    }
+
+    /// Creates a drawing with the tool or moves the one under the cursor,
+    /// returns true when the mouse is taken from the pan of the chart
+    pub fn handle_mouse(
+        &mut self,
+        d: &RaylibDrawHandle,
+        font: &Font,
+        loader: &mut Loader,
+        i: usize,
+        chart: &Chart,
+        cell: &Cell,
+    ) -> bool {
+        let mouse = d.get_mouse_position();
+        let point = point(chart, cell, mouse);
+
+        if let Some(drag) = &self.drag
+            && drag.chart == i
+        {
+            let (id, anchor) = (drag.id, drag.anchor);
+            if d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+                if let Some((time, price)) = point
+                    && let Some(drawing) = self.find_mut(id)
+                {
+                    move_anchor(drawing, anchor, time, price);
+                }
+            } else if let Some(drawing) = self.find(id).cloned() {
+                self.drag = None;
+                let empty = drawing.kind != DrawingKind::Level
+                    && (drawing.begin, drawing.begin_price) == (drawing.end, drawing.end_price);
+                if empty {
+                    self.remove(loader, id);
+                } else {
+                    save(loader, drawing);
+                }
+            } else {
+                self.drag = None;
+            }
+            return true;
+        }
+
+        if !d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+            || !cell.chart.check_collision_point_rec(mouse)
+        {
+            return false;
+        }
+        let Some((time, price)) = point else {
+            return false;
+        };
+
+        let kind = match self.tool {
+            1 => DrawingKind::Level,
+            2 => DrawingKind::Line,
+            3 => DrawingKind::Rect,
+            4 => DrawingKind::Note,
+            _ => {
+                let hit = self.hit(font, chart, cell, mouse);
+                self.selected = hit.map(|a| a.0);
+                return match hit {
+                    Some((id, anchor)) => {
+                        self.drag = Some(Drag {
+                            chart: i,
+                            id,
+                            anchor,
+                        });
+                        true
+                    }
+                    None => false,
+                };
+            }
+        };
+        let drawing = Drawing {
+            id: Uuid::new_v4(),
+            security: chart.security.clone(),
+            kind,
+            begin: time,
+            begin_price: price,
+            end: time,
+            end_price: price,
+            text: String::new(),
+        };
+        self.tool = 0;
+        self.selected = Some(drawing.id);
+        match kind {
+            DrawingKind::Level => save(loader, drawing.clone()),
+            DrawingKind::Line | DrawingKind::Rect => {
+                self.drag = Some(Drag {
+                    chart: i,
+                    id: drawing.id,
+                    anchor: Anchor::End,
+                })
+            }
+            DrawingKind::Note => self.typing = Some(drawing.id),
+        }
+        self.by_security
+            .entry(chart.security.clone())
+            .or_default()
+            .push(drawing);
+        true
+    }
+
+    /// Drawings of the security of the chart, clipped to its candles
+    pub fn draw(&self, d: &mut RaylibDrawHandle, font: &Font, chart: &Chart, cell: &Cell) {
+        let Some(drawings) = self.by_security.get(&chart.security) else {
+            return;
+        };
+        let area = cell.chart;
+        let mut d = d.begin_scissor_mode(
+            area.x as i32,
+            area.y as i32,
+            area.width as i32,
+            area.height as i32,
+        );
+
+        for drawing in drawings {
+            let Some((begin, end)) = points(chart, cell, drawing) else {
+                continue;
+            };
+            let selected = self.selected == Some(drawing.id);
+            match drawing.kind {
+                DrawingKind::Level => {
+                    let thick = if selected { 2.0 } else { 1.0 };
+                    d.draw_line_ex(
+                        Vector2::new(area.x, begin.y),
+                        Vector2::new(area.x + area.width, begin.y),
+                        thick,
+                        COLOR,
+                    );
+                    let label = format!("{:.2}", drawing.begin_price);
+                    let size = font.measure_text(&label, FONT_SIZE, 0.0);
+                    let position = Vector2::new(
+                        area.x + area.width - size.x - PADDING,
+                        begin.y - size.y - 2.0,
+                    );
+                    d.draw_text_ex(font, &label, position, FONT_SIZE, 0.0, COLOR);
+                }
+                DrawingKind::Line => d.draw_line_ex(begin, end, 2.0, COLOR),
+                DrawingKind::Rect => {
+                    let bounds = rect(begin, end);
+                    d.draw_rectangle_rec(bounds, COLOR.alpha(0.15));
+                    d.draw_rectangle_lines_ex(bounds, 1.0, COLOR);
+                }
+                DrawingKind::Note => {
+                    let text = if self.typing == Some(drawing.id) {
+                        format!("{}_", drawing.text)
+                    } else {
+                        drawing.text.clone()
+                    };
+                    let bounds = note_bounds(font, begin, &text);
+                    d.draw_rectangle_rec(bounds, BACKGROUND_COLOR.alpha(0.9));
+                    let thick = if selected { 2.0 } else { 1.0 };
+                    d.draw_rectangle_lines_ex(bounds, thick, COLOR);
+                    let position = Vector2::new(bounds.x + PADDING, bounds.y + PADDING);
+                    d.draw_text_ex(font, &text, position, FONT_SIZE, 0.0, Color::WHEAT);
+                }
+            }
+
+            let handles = matches!(drawing.kind, DrawingKind::Line | DrawingKind::Rect);
+            if selected && handles {
+                for p in [begin, end] {
+                    let handle = Rectangle::new(
+                        p.x - HANDLE_SIZE / 2.0,
+                        p.y - HANDLE_SIZE / 2.0,
+                        HANDLE_SIZE,
+                        HANDLE_SIZE,
+                    );
+                    d.draw_rectangle_rec(handle, COLOR);
+                }
+            }
+        }
+    }
+
+    /// Drawing of the chart under the cursor and its anchor, the last drawn first
+    fn hit(
+        &self,
+        font: &Font,
+        chart: &Chart,
+        cell: &Cell,
+        mouse: Vector2,
+    ) -> Option<(Uuid, Anchor)> {
+        let drawings = self.by_security.get(&chart.security)?;
+        drawings.iter().rev().find_map(|a| {
+            let (begin, end) = points(chart, cell, a)?;
+            let anchor = match a.kind {
+                DrawingKind::Level => {
+                    ((mouse.y - begin.y).abs() <= HIT_DISTANCE).then_some(Anchor::Begin)
+                }
+                DrawingKind::Note => note_bounds(font, begin, &a.text)
+                    .check_collision_point_rec(mouse)
+                    .then_some(Anchor::Begin),
+                DrawingKind::Line | DrawingKind::Rect => {
+                    if mouse.distance_to(end) <= HIT_DISTANCE {
+                        Some(Anchor::End)
+                    } else if mouse.distance_to(begin) <= HIT_DISTANCE {
+                        Some(Anchor::Begin)
+                    } else {
+                        None
+                    }
+                }
+            };
+            anchor.map(|anchor| (a.id, anchor))
+        })
+    }
+
+    fn find(&self, id: Uuid) -> Option<&Drawing> {
+        self.by_security.values().flatten().find(|a| a.id == id)
+    }
+
+    fn find_mut(&mut self, id: Uuid) -> Option<&mut Drawing> {
+        self.by_security.values_mut().flatten().find(|a| a.id == id)
+    }
+
+    fn remove(&mut self, loader: &mut Loader, id: Uuid) {
+        for drawings in self.by_security.values_mut() {
+            drawings.retain(|a| a.id != id);
+        }
+        if self.selected == Some(id) {
+            self.selected = None;
+        }
+        loader.send(Target::Drawing(id), Request::RemoveDrawing(id));
+    }
+}
+
+fn save(loader: &mut Loader, drawing: Drawing) {
+    loader.send(Target::Drawing(drawing.id), Request::SaveDrawing(drawing));
+}
+
+fn move_anchor(drawing: &mut Drawing, anchor: Anchor, time: NaiveDateTime, price: f32) {
+    match (drawing.kind, anchor) {
+        (DrawingKind::Level, _) => {
+            drawing.begin_price = price;
+            drawing.end_price = price;
+        }
+        (DrawingKind::Note, _) => {
+            (drawing.begin, drawing.begin_price) = (time, price);
+            (drawing.end, drawing.end_price) = (time, price);
+        }
+        (_, Anchor::Begin) => (drawing.begin, drawing.begin_price) = (time, price),
+        (_, Anchor::End) => (drawing.end, drawing.end_price) = (time, price),
+    }
+}
+
+/// Time of the candle and the price under the cursor
+fn point(chart: &Chart, cell: &Cell, mouse: Vector2) -> Option<(NaiveDateTime, f32)> {
+    let coords = &chart.coords;
+    let time = chart.viewport.x_time(&cell.chart, mouse.x)?;
+    let price = convert_value_y(coords.start_pos.y, coords.step_y, coords.max_y, mouse.y);
+    Some((time, price))
+}
+
+/// Anchors of the drawing on the screen, levels span the whole chart
+fn points(chart: &Chart, cell: &Cell, drawing: &Drawing) -> Option<(Vector2, Vector2)> {
+    let coords = &chart.coords;
+    let y = |price| convert_coords_y(coords.start_pos.y, coords.step_y, coords.max_y, price);
+    let x = |time| match drawing.kind {
+        DrawingKind::Level => Some(cell.chart.x),
+        _ => chart.viewport.time_x(&cell.chart, time),
+    };
+    Some((
+        Vector2::new(x(drawing.begin)?, y(drawing.begin_price)),
+        Vector2::new(x(drawing.end)?, y(drawing.end_price)),
+    ))
+}
+
+fn rect(begin: Vector2, end: Vector2) -> Rectangle {
+    Rectangle::new(
+        begin.x.min(end.x),
+        begin.y.min(end.y),
+        (end.x - begin.x).abs(),
+        (end.y - begin.y).abs(),
+    )
+}
+
+fn note_bounds(font: &Font, position: Vector2, text: &str) -> Rectangle {
+    let size = font.measure_text(text, FONT_SIZE, 0.0);
+    Rectangle::new(
+        position.x,
+        position.y,
+        size.x.max(FONT_SIZE) + PADDING * 2.0,
+        FONT_SIZE + PADDING * 2.0,
+    )
+}
@@ -0,0 +1,201 @@
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+const LAYOUT_PATH: &str = "terminal/layout.json";
+const SIDEBAR_W: f32 = 300.0;
+/// Space left of a chart for the y-axis labels
+const AXIS_W: f32 = 60.0;
+const MARGIN: f32 = 20.0;
+/// Space above a chart for its dropdowns
+const HEADER_H: f32 = 40.0;
+/// Space under a panel for the time labels
+const LABELS_H: f32 = 60.0;
+const MIN_CHART_RATIO: f32 = 0.2;
+const MAX_CHART_RATIO: f32 = 0.8;
+/// Distance to the splitter in pixels to start dragging it
+const SPLITTER_DISTANCE: f32 = 6.0;
+pub const MIN_W: i32 = 800;
+pub const MIN_H: i32 = 740;
+pub const MAX_CHARTS: usize = 4;
+
+/// Part of the layout saved between sessions
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutSettings {
+    pub width: i32,
+    pub height: i32,
+    /// part of the height of the panels taken by the candles,
+    /// the rest is shared by the trades and the indicator panel
+    pub chart_ratio: f32,
+    /// number of charts in the split view, 1..=MAX_CHARTS
+    pub charts: usize,
+}
+
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 800,
+            chart_ratio: 0.5,
+            charts: 1,
+        }
+    }
+}
+
+/// Areas of one chart of the split view
+#[derive(Debug, Clone, Copy)]
+pub struct Cell {
+    pub securities: Rectangle,
+    pub frames: Rectangle,
+    pub chart: Rectangle,
+    pub trades: Rectangle,
+    pub panel: Rectangle,
+    /// height of the chart, trades and panel together
+    content_h: f32,
+}
+
+impl Cell {
+    /// The whole cell: dropdowns, axis labels, candles, trades and the panel
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(
+            self.chart.x - AXIS_W,
+            self.securities.y,
+            self.chart.width + AXIS_W,
+            self.panel.y + self.panel.height + LABELS_H - self.securities.y,
+        )
+    }
+}
+
+/// Areas of the window computed from its size: the sidebar with the ui on the left,
+/// the charts in a grid on the right, each with candles, trades and the indicator
+/// panel stacked under its dropdowns
+pub struct Layout {
+    pub settings: LayoutSettings,
+    pub cells: Vec<Cell>,
+    pub split: Rectangle,
+    pub sync: Rectangle,
+    pub range: Vector2,
+    pub info: Vector2,
+    pub overlays: Vector2,
+    pub attempts: Rectangle,
+    pub tools: Rectangle,
+    pub replay: Vector2,
+    pub paper: Vector2,
+    /// cell whose splitter is dragged
+    drag: Option<usize>,
+}
+
+impl Layout {
+    pub fn new(settings: LayoutSettings) -> Self {
+        let mut layout = Self {
+            settings,
+            cells: vec![],
+            split: Rectangle::new(25.0, 25.0, 38.0, 30.0),
+            sync: Rectangle::new(25.0, 70.0, 15.0, 15.0),
+            range: Vector2::new(25.0, 100.0),
+            info: Vector2::new(25.0, 250.0),
+            overlays: Vector2::new(25.0, 330.0),
+            attempts: Rectangle::new(25.0, 460.0, 165.0, 30.0),
+            tools: Rectangle::new(25.0, 510.0, 48.0, 30.0),
+            replay: Vector2::new(25.0, 555.0),
+            paper: Vector2::new(25.0, 650.0),
+            drag: None,
+        };
+        layout.settings.charts = layout.settings.charts.clamp(1, MAX_CHARTS);
+        layout.update();
+        layout
+    }
+
+    /// Settings of the previous session or the default ones
+    pub fn load() -> Self {
+        let settings = match fs::read_to_string(LAYOUT_PATH) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                println!("[ERROR]: {e}, failed to parse {LAYOUT_PATH}");
+                LayoutSettings::default()
+            }),
+            Err(_) => LayoutSettings::default(),
+        };
+        Self::new(settings)
+    }
+
+    pub fn save(&self) {
+        let json =
+            serde_json::to_string_pretty(&self.settings).expect("failed to serialize layout");
+        if let Err(e) = fs::write(LAYOUT_PATH, json) {
+            println!("[ERROR]: {e}, failed to save {LAYOUT_PATH}");
+        }
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.settings.width = width;
+        self.settings.height = height;
+        self.update();
+    }
+
+    pub fn set_charts(&mut self, charts: usize) {
+        self.settings.charts = charts.clamp(1, MAX_CHARTS);
+        self.update();
+    }
+
+    /// Drag of the border between the candles and the trades of any chart,
+    /// returns true when the layout changed
+    pub fn handle_input(&mut self, d: &RaylibDrawHandle) -> bool {
+        let mouse = d.get_mouse_position();
+        if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.drag = self.cells.iter().position(|a| {
+                let splitter = a.trades.y - LABELS_H / 2.0;
+                mouse.x >= a.chart.x
+                    && mouse.x <= a.chart.x + a.chart.width
+                    && (mouse.y - splitter).abs() <= SPLITTER_DISTANCE
+            });
+        }
+        if !d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.drag = None;
+        }
+        let cell = match self.drag {
+            Some(i) if d.get_mouse_delta().y != 0.0 => self.cells[i],
+            _ => return false,
+        };
+
+        let chart_h = mouse.y - LABELS_H / 2.0 - cell.chart.y;
+        self.settings.chart_ratio =
+            (chart_h / cell.content_h).clamp(MIN_CHART_RATIO, MAX_CHART_RATIO);
+        self.update();
+        true
+    }
+
+    fn update(&mut self) {
+        let (columns, rows) = match self.settings.charts {
+            1 => (1, 1),
+            2 => (2, 1),
+            3 => (3, 1),
+            _ => (2, 2),
+        };
+        let left = SIDEBAR_W - AXIS_W;
+        let cell_w = (self.settings.width as f32 - left) / columns as f32;
+        let cell_h = self.settings.height as f32 / rows as f32;
+
+        self.cells = (0..self.settings.charts)
+            .map(|i| {
+                let x = left + (i % columns) as f32 * cell_w + AXIS_W;
+                let y = (i / columns) as f32 * cell_h + MARGIN;
+                let width = cell_w - AXIS_W - MARGIN;
+                let content_h = cell_h - MARGIN - HEADER_H - LABELS_H * 3.0;
+                let chart_h = content_h * self.settings.chart_ratio;
+                let panel_h = (content_h - chart_h) / 2.0;
+
+                let chart = Rectangle::new(x, y + HEADER_H, width, chart_h);
+                let trades = Rectangle::new(x, chart.y + chart_h + LABELS_H, width, panel_h);
+                Cell {
+                    securities: Rectangle::new(x, y, 80.0, 30.0),
+                    frames: Rectangle::new(x + 85.0, y, 80.0, 30.0),
+                    chart,
+                    trades,
+                    panel: Rectangle::new(x, trades.y + panel_h + LABELS_H, width, panel_h),
+                    content_h,
+                }
+            })
+            .collect();
+    }
+}
@@ -1,21 +1,43 @@
+mod chart;
+mod commands;
+mod crosshair;
+mod drawings;
+mod layout;
+mod loader;
+mod operations;
+mod overlays;
+mod paper;
+mod range;
+mod replay;
+mod snapshot;
+mod viewport;
+
 use app::db::pg;
-use app::models::common::{Candle, Frame, TradeView};
-use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use app::models::common::{Candle, Frame, Operation, TradeView};
+use chart::Chart;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use commands::{Command, Commands};
+use crosshair::{draw_crosshair, draw_time_cursor};
+use drawings::{Drawings, TOOLS};
+use layout::{Layout, MAX_CHARTS, MIN_H, MIN_W};
+use loader::{Loader, Request, Response, Target};
+use operations::{ATTEMPTS_LIMIT, attempts_list};
+use overlays::{Overlays, draw_overlays_ui};
+use paper::Paper;
+use range::DateRange;
 use raylib::prelude::GuiControlProperty::*;
 use raylib::prelude::GuiTextAlignment::*;
 use raylib::prelude::*;
 use regex::Regex;
+use replay::Replay;
 use sqlx::PgPool;
 use std::i64;
-use std::time::Duration;
 
-const H: f32 = 640.0;
-const W: f32 = 1280.0;
 const CANDLE_W: f32 = 12.0;
 const COUNT_Y: f32 = 10.0;
 const DATE_TIME_FMT: &str = "%Y-%m-%d %H:%M:%S";
 const DATE_FMT: &str = "%Y-%m-%d";
-const TRADES_DELTA_Y: f32 = 300.0;
+const SPLITS: &str = "1;2;3;4";
 const BACKGROUND_COLOR: Color = Color::new(23, 35, 46, 0);
 
 #[allow(dead_code)]
@@ -26,19 +48,12 @@ struct DrawCoords {
     step_y: f32,
     min_y: f32,
     max_y: f32,
+    candle_w: f32,
 }
 
-#[allow(dead_code)]
 struct UiElements<'a> {
     securities: &'a str,
-    selected_security: &'a str,
     secs: Vec<&'a str>,
-    securities_edit: bool,
-    securities_active: i32,
-    begin_str: String,
-    begin_edit: bool,
-    end_str: String,
-    end_edit: bool,
 }
 
 pub async fn run_terminal(pool: &PgPool) {
@@ -48,73 +63,56 @@ pub async fn run_terminal(pool: &PgPool) {
     let secs: Vec<&str> = securities.split(";").collect();
     let selected_security = &start_info.security_code; //secs[0];
 
-    let frames_str = "m1;m15;h1;d1";
-    let frames = &frames_str.split(";").collect::<Vec<&str>>();
-    let mut frame_active: i32 = 1;
-    let mut current_frame = frames[frame_active as usize];
-    let mut frame_edit: bool = false;
-
-    let mut date_active: i32 = 0;
-    let mut date_edit: bool = false;
-    let mut dates = &start_info.dates.split(";").collect::<Vec<&str>>();
-    let mut current_date = dates[date_active as usize];
-    let mut begin =
-        NaiveDateTime::parse_from_str(&format!("{} 00:00:00", current_date), DATE_TIME_FMT)
-            .unwrap();
-    let mut end = begin + Duration::from_secs(60 * 60 * 24 * 1);
-
-    let data = fetch_data(
-        pool,
-        selected_security,
-        begin,
-        end,
-        &Frame::from(current_frame),
-    )
-    .await;
-
-    if !data.is_some() {
-        println!("Has no data for: {:#}, {}", begin, selected_security);
-        return;
-    }
+    let mut range = DateRange::new(&start_info.dates);
+
+    let mut layout = Layout::load();
 
-    let (mut candles, mut coords) = data.unwrap();
+    let security_active = secs
+        .iter()
+        .position(|a| *a == selected_security.as_str())
+        .unwrap_or(0);
 
-    let mut trades = pg::get_trades_view(
-        pool,
-        selected_security,
-        begin,
-        end,
-        &Frame::from(current_frame),
-        candles.len() as i32,
-    )
-    .await;
+    // все запросы к базе в цикле отрисовки идут через loader
+    let mut loader = Loader::new(pool);
+    let mut charts: Vec<Chart> = vec![];
+    let after = security_active + secs.len() - 1;
+    add_charts(&mut loader, &charts, &layout, &secs, &range, after);
+    let mut split_active = layout.cells.len() as i32 - 1;
+    let mut active: usize = 0;
+    let mut sync: bool = false;
 
     // ui
     let alpha = 1.0;
-    let mut ui = UiElements {
+    let ui = UiElements {
         securities: &securities,
-        selected_security,
         secs,
-        securities_edit: false,
-        securities_active: 0,
-        begin_str: format!("{} ", begin.format(DATE_TIME_FMT)),
-        begin_edit: false,
-        end_str: format!("{} ", end.format(DATE_TIME_FMT)),
-        end_edit: false,
     };
 
     let (mut rl, thread) = raylib::init()
-        .size(W as i32, H as i32)
+        .size(layout.settings.width, layout.settings.height)
         .title("Trading terminal")
+        .resizable()
         .build();
 
+    rl.set_window_min_size(MIN_W, MIN_H);
+
     rl.set_target_fps(60);
 
     let font = rl
         .load_font(&thread, "terminal/assets/fonts/SourceCodePro-Bold.ttf")
         .expect("failed to load font");
     let mut info = String::from("");
-    let mut current_candle = candles.first().unwrap().clone();
+    let mut overlays = Overlays::default();
+    let mut commands = Commands::default();
+    let mut drawings = Drawings::default();
+    let mut replay = Replay::default();
+    let mut paper = Paper::default();
+
+    let mut attempts = pg::get_attempts(pool, ATTEMPTS_LIMIT).await;
+    let mut attempts_str = attempts_list(&attempts);
+    let mut attempt_active: i32 = 0;
+    let mut attempt_edit: bool = false;
+    let mut operations: Vec<Operation> = vec![];
 
     rl.gui_set_font(&font);
     rl.gui_set_style(GuiControl::DEFAULT, GuiDefaultProperty::TEXT_SIZE, 15);
@@ -124,211 +122,462 @@ pub async fn run_terminal(pool: &PgPool) {
         d.clear_background(BACKGROUND_COLOR);
         d.gui_set_alpha(alpha);
 
+        if d.is_window_resized() {
+            layout.resize(d.get_screen_width(), d.get_screen_height());
+            for (chart, cell) in charts.iter_mut().zip(layout.cells.iter()) {
+                chart.update_view(cell);
+            }
+        }
+
+        // answers of the loader
+        for (target, response) in loader.poll() {
+            match response {
+                Err(e) => info = e,
+                Ok(Response::Charts {
+                    frame_active,
+                    charts: loaded,
+                }) => {
+                    for (security_active, security, viewport) in loaded {
+                        match target {
+                            Target::Add if charts.len() < layout.cells.len() => {
+                                let cell = &layout.cells[charts.len()];
+                                charts.push(Chart::new(
+                                    security_active,
+                                    security,
+                                    frame_active,
+                                    viewport,
+                                    cell,
+                                ));
+                            }
+                            Target::Chart(i) if i < charts.len() => {
+                                charts[i].set(
+                                    security_active,
+                                    security,
+                                    frame_active,
+                                    viewport,
+                                    &layout.cells[i],
+                                );
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Ok(Response::Chunk {
+                    side,
+                    begin,
+                    end,
+                    candles,
+                    trades,
+                }) => {
+                    if let Target::More(i) = target
+                        && let Some(chart) = charts.get_mut(i)
+                        && chart.viewport.add_chunk(side, begin, end, candles, trades)
+                    {
+                        chart.update_history(&layout.cells[i]);
+                    }
+                }
+                Ok(Response::Operations(loaded)) => operations = loaded,
+                Ok(Response::Drawings {
+                    security,
+                    drawings: loaded,
+                }) => drawings.add(security, loaded),
+                Ok(Response::Ticks { security, ticks }) => replay.add_ticks(security, ticks),
+                Ok(Response::Attempts(loaded)) if target == Target::Attempts => {
+                    // выбранная попытка остается выбранной, если она есть в новом списке
+                    let selected = (attempt_active as usize)
+                        .checked_sub(1)
+                        .and_then(|i| attempts.get(i))
+                        .map(|a| a.id);
+                    attempts = loaded;
+                    attempts_str = attempts_list(&attempts);
+                    attempt_active = selected
+                        .and_then(|id| attempts.iter().position(|a| a.id == id))
+                        .map_or(0, |i| i as i32 + 1);
+                    if attempt_active == 0 {
+                        loader.cancel(Target::Operations);
+                        operations = vec![];
+                    }
+                }
+                Ok(Response::Attempts(saved)) => {
+                    info = format!("saved paper trades: {} attempts", saved.len());
+                    // новые попытки в начале списка, выбранная остается выбранной
+                    if attempt_active > 0 {
+                        attempt_active += saved.len() as i32;
+                    }
+                    attempts.splice(0..0, saved);
+                    attempts_str = attempts_list(&attempts);
+                }
+                Ok(Response::Done) => {}
+            }
+            if target == Target::Add && charts.len() < layout.cells.len() {
+                layout.set_charts(charts.len());
+                split_active = layout.cells.len() as i32 - 1;
+                for (chart, cell) in charts.iter_mut().zip(layout.cells.iter()) {
+                    chart.update_view(cell);
+                }
+            }
+        }
+
+        drawings.load(&mut loader, &charts);
+        replay.load(&mut loader, &charts, &range);
+
+        // hotkeys, palette and the text of notes
+        let editing = charts.iter().any(|a| a.is_editing()) || range.is_editing() || attempt_edit;
+        if !editing && !commands.is_open() {
+            drawings.handle_keys(&mut d, &mut loader);
+        }
+        let command = if editing || drawings.is_typing() {
+            None
+        } else {
+            commands.handle_input(&mut d, &ui.secs)
+        };
+        let editing = editing || commands.is_open() || drawings.is_typing();
+        let mut range_changed = false;
+        let mut snapshot = false;
+        if let Some(command) = command {
+            let selected = charts
+                .get(active)
+                .map(|a| (a.security_active, a.frame_active));
+            let count = ui.secs.len() as i32;
+            let reloaded = match (command, selected) {
+                (Command::Security(s), Some((_, f))) => Some((s, f)),
+                (Command::NextSecurity, Some((s, f))) => Some(((s + 1) % count, f)),
+                (Command::PrevSecurity, Some((s, f))) => Some(((s + count - 1) % count, f)),
+                (Command::Frame(f), Some((s, _))) => Some((s, f)),
+                _ => None,
+            };
+            if let Some((security_active, frame_active)) = reloaded {
+                reload(
+                    &mut loader,
+                    active,
+                    security_active,
+                    frame_active,
+                    &ui.secs,
+                    &range,
+                );
+            }
+            match command {
+                Command::NextDate => range_changed = range.step_session(1),
+                Command::PrevDate => range_changed = range.step_session(-1),
+                Command::Date(date) => {
+                    range.set_day(date);
+                    range_changed = true;
+                }
+                Command::Preset(i) => range_changed = range.set_preset(i),
+                Command::Split(count) => split_active = count as i32 - 1,
+                Command::SyncCursor => sync = !sync,
+                Command::RefreshAttempts => {
+                    loader.send(Target::Attempts, Request::Attempts(ATTEMPTS_LIMIT))
+                }
+                Command::Replay
+                | Command::ReplayPlay
+                | Command::ReplayStep
+                | Command::PaperBuy
+                | Command::PaperSell
+                | Command::PaperSave => {
+                    if let Some(message) = paper.run(command, &replay, &charts, active, &mut loader)
+                    {
+                        info = message;
+                    }
+                    replay.run(command, range.begin, &charts, active, &loader);
+                }
+                // снимок после отрисовки графиков, но без перекрестия
+                Command::SnapshotPng => snapshot = true,
+                Command::ExportSvg => {
+                    if let Some(chart) = charts.get(active) {
+                        let chart_drawings = drawings.of(&chart.security);
+                        info = match snapshot::export_svg(
+                            chart,
+                            &overlays,
+                            &replay.operations(&operations),
+                            chart_drawings,
+                        ) {
+                            Ok(path) => format!("saved {}", path.display()),
+                            Err(e) => e,
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+
         //draw ui
-        if ui.securities_edit || frame_edit {
+        if editing {
             d.gui_lock();
         }
 
-        // if draw_datepicker(
-        //     &mut d,
-        //     Vector2::new(25.0, 90.0),
-        //     &mut ui.begin_str,
-        //     &mut ui.begin_edit,
-        //     "BEGIN",
-        //     &mut begin,
-        //     &font,
-        // ) {
-        //     (candles, coords) = fetch_data(
-        //         pool,
-        //         ui.selected_security,
-        //         begin,
-        //         end,
-        //         &Frame::from(current_frame),
-        //     )
-        //     .await
-        //     .unwrap();
-        // }
-
-        // if draw_datepicker(
-        //     &mut d,
-        //     Vector2::new(25.0, 135.0),
-        //     &mut ui.end_str,
-        //     &mut ui.end_edit,
-        //     "END",
-        //     &mut end,
-        //     &font,
-        // ) {
-        //     (candles, coords) = fetch_data(
-        //         pool,
-        //         ui.selected_security,
-        //         begin,
-        //         end,
-        //         &Frame::from(current_frame),
-        //     )
-        //     .await
-        //     .unwrap();
-        // }
+        d.gui_toggle_group(layout.split, SPLITS, &mut split_active);
+        if split_active as usize + 1 != layout.cells.len() {
+            layout.set_charts(split_active as usize + 1);
+            for i in layout.cells.len()..MAX_CHARTS {
+                loader.cancel(Target::Chart(i));
+                loader.cancel(Target::More(i));
+            }
+            charts.truncate(layout.cells.len());
+            let after = charts.last().map_or(0, |a| a.security_active as usize);
+            add_charts(&mut loader, &charts, &layout, &ui.secs, &range, after);
+            active = active.min(layout.cells.len() - 1);
+            for (chart, cell) in charts.iter_mut().zip(layout.cells.iter()) {
+                chart.update_view(cell);
+            }
+        }
+        d.gui_check_box(layout.sync, "SYNC CURSOR", &mut sync);
+
+        if range.draw(&mut d, &font, layout.range) || range_changed {
+            for (i, chart) in charts.iter().enumerate() {
+                let (security_active, frame_active) = (chart.security_active, chart.frame_active);
+                reload(
+                    &mut loader,
+                    i,
+                    security_active,
+                    frame_active,
+                    &ui.secs,
+                    &range,
+                );
+            }
+            if loader.is_loading(Target::Add) {
+                let after = charts.last().map_or(0, |a| a.security_active as usize);
+                add_charts(&mut loader, &charts, &layout, &ui.secs, &range, after);
+            }
+            replay.clear_ticks(&mut loader, &ui.secs);
+            if replay.enabled {
+                // сделки прошлого диапазона сохраняются до перезапуска replay
+                paper.run(Command::PaperSave, &replay, &charts, active, &mut loader);
+                replay.restart(range.begin, &charts, active, &loader);
+            }
+        }
 
-        if draw_dropdown(
-            &mut d,
-            ui.securities,
-            &mut ui.securities_active,
-            &mut ui.securities_edit,
-            Rectangle::new(25.0, 25.0, 80.0, 30.0),
-            false,
-        ) {
-            ui.securities_edit = !ui.securities_edit;
-            if ui.secs[ui.securities_active as usize] != ui.selected_security {
-                ui.selected_security = ui.secs[ui.securities_active as usize];
-                (candles, coords) = fetch_data(
-                    pool,
-                    ui.selected_security,
-                    begin,
-                    end,
-                    &Frame::from(current_frame),
-                )
-                .await
-                .unwrap();
+        d.gui_toggle_group(layout.tools, TOOLS, &mut drawings.tool);
+        let loading = replay.is_loading(&loader, &charts);
+        let replay_command = replay.draw(&mut d, &font, layout.replay, loading);
+        let paper_command = paper.draw(&mut d, &font, layout.paper, &replay, charts.get(active));
+        if let Some(command) = replay_command.or(paper_command) {
+            if let Some(message) = paper.run(command, &replay, &charts, active, &mut loader) {
+                info = message;
             }
+            replay.run(command, range.begin, &charts, active, &loader);
         }
+        draw_overlays_ui(&mut d, &mut overlays, layout.overlays);
 
         if draw_dropdown(
             &mut d,
-            frames_str,
-            &mut frame_active,
-            &mut frame_edit,
-            Rectangle::new(110.0, 25.0, 80.0, 30.0),
+            &attempts_str,
+            &mut attempt_active,
+            &mut attempt_edit,
+            layout.attempts,
             false,
         ) {
-            frame_edit = !frame_edit;
-            if frames[frame_active as usize] != current_frame {
-                current_frame = frames[frame_active as usize];
-                (candles, coords) = fetch_data(
-                    pool,
-                    ui.selected_security,
-                    begin,
-                    end,
-                    &Frame::from(current_frame),
-                )
-                .await
-                .unwrap();
+            attempt_edit = !attempt_edit;
+            match attempt_active {
+                0 => {
+                    loader.cancel(Target::Operations);
+                    operations = vec![];
+                }
+                i => loader.send(
+                    Target::Operations,
+                    Request::Operations(attempts[i as usize - 1].id),
+                ),
             }
         }
 
-        let bounds = Rectangle::new(25.0, 200.0, 130.0, 30.0);
-        if draw_dropdown(
-            &mut d,
-            &start_info.dates,
-            &mut date_active,
-            &mut date_edit,
-            bounds,
-            true,
-        ) {
-            date_edit = !date_edit;
-            // if date_edit {
-            let scrollPanelBounds: Rectangle =
-                Rectangle::new(bounds.x, bounds.y + bounds.height, bounds.width, 150.0);
-
-            let content = Rectangle::new(bounds.x, bounds.y + bounds.height, bounds.width, 200.0);
-            let scroll = Vector2::new(bounds.x, bounds.y);
-            let view = Rectangle::new(bounds.x, bounds.y + bounds.height, bounds.width, 150.0);
-            let _ = d.gui_scroll_panel(scrollPanelBounds, &start_info.dates, content, scroll, view);
-            // }
-            if dates[date_active as usize] != current_date {
-                current_date = dates[date_active as usize];
-                begin = NaiveDateTime::parse_from_str(
-                    &format!("{} 00:00:00", current_date),
-                    DATE_TIME_FMT,
-                )
-                .unwrap();
-                end = begin + Duration::from_secs(60 * 60 * 24 * 1);
-                (candles, coords) = fetch_data(
-                    pool,
-                    ui.selected_security,
-                    begin,
-                    end,
-                    &Frame::from(current_frame),
-                )
-                .await
-                .unwrap();
+        // pan, zoom and splitter
+        if !editing {
+            if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                let mouse = d.get_mouse_position();
+                if let Some(i) = layout
+                    .cells
+                    .iter()
+                    .position(|a| a.chart.check_collision_point_rec(mouse))
+                {
+                    active = i;
+                }
+            }
+            let resized = layout.handle_input(&d);
+            for (i, (chart, cell)) in charts.iter_mut().zip(layout.cells.iter()).enumerate() {
+                let frame = chart.frame();
+                let drawn = drawings.handle_mouse(&d, &font, &mut loader, i, chart, cell);
+                let moved = !drawn && chart.viewport.handle_input(&d, &cell.chart, i == active);
+                if resized || moved {
+                    chart.update_view(cell);
+                }
+                // пока грузится новый график, куски старого не нужны
+                if !loader.is_loading(Target::Chart(i))
+                    && !loader.is_loading(Target::More(i))
+                    && let Some((side, begin, end)) = chart.viewport.next_chunk(&frame, &cell.chart)
+                {
+                    let request = Request::Chunk {
+                        security: chart.security.clone(),
+                        frame,
+                        side,
+                        begin,
+                        end,
+                    };
+                    loader.send(Target::More(i), request);
+                }
             }
-            // let position = Rectangle::new(35.0, 200.0, 130.0, 30.0);
-            // let mut bounds = position;
-            // bounds.height = 500.0;
-            // let scroll = Vector2::new(bounds.x, bounds.y);
-            // d.gui_scroll_panel(bounds, "", bounds, scroll, bounds);
         }
 
-        // candles
-        draw_axis(&mut d, &font, &coords);
-        draw_graphs(
-            &mut d,
-            &coords,
-            &mut candles,
-            &Frame::from(current_frame),
-            &font,
-            &current_candle,
-        );
+        // replay hides the future
+        replay.update(d.get_frame_time(), &charts, active, &loader);
+        replay.apply(&mut charts, &layout.cells);
+        paper.update(&replay, &charts);
+        let mut shown_operations = replay.operations(&operations);
+        if paper.operations().next().is_some() {
+            shown_operations
+                .to_mut()
+                .extend(paper.operations().cloned());
+        }
 
-        // trades
-        draw_trades(&mut d, &font, &trades, &coords, &Frame::from(current_frame));
-
-        if mouse_click(&mut d, &coords, &candles, &mut current_candle, &mut info) {
-            // trades = pg::get_trades_view(
-            //     pool,
-            //     selected_security,
-            //     current_candle.begin,
-            //     current_candle.end,
-            //     &Frame::from(current_frame),
-            //     candles.len() as i32,
-            // )
-            // .await;
+        // charts
+        for (i, (chart, cell)) in charts.iter_mut().zip(layout.cells.iter()).enumerate() {
+            chart.draw(&mut d, &font, cell, &overlays, &shown_operations);
+            drawings.draw(&mut d, &font, chart, cell);
+            if i == active && layout.cells.len() > 1 {
+                d.draw_rectangle_lines_ex(cell.chart, 1.0, Color::SKYBLUE.alpha(0.4));
+            }
+            mouse_click(
+                &mut d,
+                &chart.coords,
+                &chart.candles,
+                &mut chart.current_candle,
+                &mut info,
+            );
         }
 
-        draw_info(&mut d, &coords, &font, &info);
-        // draw_arrow(&mut d, &mut current_candle);
-    }
-}
+        draw_info(&mut d, layout.info, &font, &info);
+
+        // loading state
+        for (i, cell) in layout.cells.iter().enumerate() {
+            if loader.is_loading(Target::Chart(i))
+                || loader.is_loading(Target::More(i))
+                || (i >= charts.len() && loader.is_loading(Target::Add))
+            {
+                let position = Vector2::new(
+                    cell.frames.x + cell.frames.width + 15.0,
+                    cell.frames.y + 8.0,
+                );
+                draw_loading(&mut d, &font, position);
+            }
+        }
+        if loader.is_loading(Target::Operations) || loader.is_loading(Target::Attempts) {
+            let attempts = layout.attempts;
+            let position = Vector2::new(attempts.x + attempts.width + 10.0, attempts.y + 8.0);
+            draw_loading(&mut d, &font, position);
+        }
 
-async fn fetch_data<'a>(
-    pool: &'a PgPool,
-    security: &'a str,
-    begin: NaiveDateTime,
-    end: NaiveDateTime,
-    frame: &Frame,
-) -> Option<(Vec<Candle>, DrawCoords)> {
-    let start_pos = Vector2::new(300.0, 20.0);
-    let end_pos = Vector2::new(W - 20.0, 240.0 - 20.0);
-    let limit = ((end_pos.x - start_pos.x) / CANDLE_W) as i32 - 1;
-    let candles = pg::get_candles(pool, &security, begin, end, limit, frame).await;
-    if candles.is_empty() {
-        return None;
-    }
-    let mut min_low: f32 = candles.first().unwrap().low;
-    let mut max_high: f32 = 0_f32;
+        let mut cursor = None;
+        for (i, (chart, cell)) in charts.iter().zip(layout.cells.iter()).enumerate() {
+            if !snapshot && let Some(time) = draw_crosshair(&mut d, &font, &layout, cell, chart) {
+                cursor = Some((i, time));
+            }
+        }
+        if sync && let Some((hovered, time)) = cursor {
+            for (i, (chart, cell)) in charts.iter().zip(layout.cells.iter()).enumerate() {
+                if i != hovered {
+                    draw_time_cursor(&mut d, cell, chart, time);
+                }
+            }
+        }
 
-    for candle in candles.iter() {
-        if candle.low < min_low {
-            min_low = candle.low;
+        // dropdowns over the charts
+        for (i, (chart, cell)) in charts.iter_mut().zip(layout.cells.iter()).enumerate() {
+            if let Some((security_active, frame_active)) =
+                chart.draw_header(&mut d, ui.securities, cell)
+            {
+                reload(
+                    &mut loader,
+                    i,
+                    security_active,
+                    frame_active,
+                    &ui.secs,
+                    &range,
+                );
+            }
         }
-        if candle.high > max_high {
-            max_high = candle.high;
+
+        if snapshot
+            && let (Some(chart), Some(cell)) = (charts.get(active), layout.cells.get(active))
+        {
+            info = match snapshot::save_png(&mut d, &thread, cell, chart) {
+                Ok(path) => format!("saved {}", path.display()),
+                Err(e) => e,
+            };
+        }
+
+        commands.draw(&mut d, &font, layout.settings.width as f32, &ui.secs);
+        let overlay = commands.has_overlay() || drawings.is_typing();
+        let exit_key = (!overlay).then_some(KeyboardKey::KEY_ESCAPE);
+        d.set_exit_key(exit_key);
+    }
+
+    layout.save();
+    // начатые сохранения сделок должны закончиться до остановки runtime
+    let saves = loader.wait(|a| matches!(a, Target::Paper(_))).await;
+    for (_, response) in saves {
+        if let Err(e) = response {
+            println!("[ERROR]: {e}, failed to save paper trades");
         }
     }
+    paper.save_on_exit(pool, &replay).await;
+}
 
-    let min_y = f32::floor(min_low);
-    let max_y = f32::ceil(max_high);
-    let step_y = (end_pos.y - start_pos.y) / (max_y - min_y);
+/// Requests charts for the empty cells of the layout, each with the next security
+/// after `after` that has data, the layout shrinks on the answer if there are
+/// not enough of them
+fn add_charts(
+    loader: &mut Loader,
+    charts: &[Chart],
+    layout: &Layout,
+    secs: &[&str],
+    range: &DateRange,
+    after: usize,
+) {
+    let count = layout.cells.len().saturating_sub(charts.len());
+    if count == 0 {
+        loader.cancel(Target::Add);
+        return;
+    }
+    let candidates = (1..=secs.len())
+        .map(|k| {
+            let i = (after + k) % secs.len();
+            (i as i32, secs[i].to_string())
+        })
+        .collect();
+    let request = Request::Charts {
+        count,
+        candidates,
+        frame_active: charts.first().map_or(1, |a| a.frame_active),
+        begin: range.begin,
+        end: range.end,
+    };
+    loader.send(Target::Add, request);
+}
 
-    let coords = DrawCoords {
-        start_pos,
-        end_pos,
-        step_y,
-        min_y,
-        max_y,
+/// Requests the candles of the chart for the security, frame and range,
+/// the chart keeps the current ones until the answer. A range without data
+/// comes back as an error for the info line and the chart stays as it was
+fn reload(
+    loader: &mut Loader,
+    i: usize,
+    security_active: i32,
+    frame_active: i32,
+    secs: &[&str],
+    range: &DateRange,
+) {
+    loader.cancel(Target::More(i));
+    let security = secs[security_active as usize].to_string();
+    let request = Request::Charts {
+        count: 1,
+        candidates: vec![(security_active, security)],
+        frame_active,
+        begin: range.begin,
+        end: range.end,
     };
+    loader.send(Target::Chart(i), request);
+}
 
-    return Some((candles, coords));
+fn draw_loading(d: &mut RaylibDrawHandle, font: &Font, position: Vector2) {
+    d.draw_text_ex(font, "LOADING...", position, 15.0, 0.0, Color::WHEAT);
 }
 
 fn draw_axis(d: &mut RaylibDrawHandle, font: &Font, coords: &DrawCoords) {
@@ -397,8 +646,8 @@ fn draw_axis(d: &mut RaylibDrawHandle, font: &Font, coords: &DrawCoords) {
             );
         }
 
-        right += CANDLE_W;
-        left -= CANDLE_W;
+        right += coords.candle_w;
+        left -= coords.candle_w;
         i += 1;
     }
 }
@@ -407,6 +656,16 @@ fn convert_coords_y(start: f32, step: f32, max: f32, value: f32) -> f32 {
     (max - value) * step + start
 }
 
+/// Inverse of `convert_coords_y`
+fn convert_value_y(start: f32, step: f32, max: f32, y: f32) -> f32 {
+    max - (y - start) / step
+}
+
+/// Center of the candle with index `i`, same as `draw_graphs`
+fn candle_x(coords: &DrawCoords, i: usize) -> f32 {
+    coords.start_pos.x + (i as f32 * coords.candle_w) + coords.candle_w * 1.5
+}
+
 fn draw_graphs(
     d: &mut RaylibDrawHandle,
     coords: &DrawCoords,
@@ -420,16 +679,8 @@ fn draw_graphs(
     let mut month: u32 = 0;
 
     for (i, candle) in candles.into_iter().enumerate() {
-        let x = coords.start_pos.x + (i as f32 * CANDLE_W);
-        draw_candle(
-            d,
-            candle,
-            x + CANDLE_W,
-            coords.start_pos,
-            coords.step_y,
-            coords.max_y,
-            current_candle,
-        );
+        let x = coords.start_pos.x + (i as f32 * coords.candle_w);
+        draw_candle(d, candle, x + coords.candle_w, coords, current_candle);
 
         // print time labels on x-axis
         match frame {
@@ -445,11 +696,10 @@ fn draw_candle(
     d: &mut RaylibDrawHandle,
     candle: &mut Candle,
     idx_pos: f32,
-    start_pos: Vector2,
-    step_y: f32,
-    max_y: f32,
+    coords: &DrawCoords,
     current_candle: &Candle,
 ) {
+    let (start_pos, step_y, max_y) = (coords.start_pos, coords.step_y, coords.max_y);
     let max = f32::max(candle.close, candle.open);
     let min = f32::min(candle.close, candle.open);
     let color = if candle.begin == current_candle.begin {
@@ -460,16 +710,16 @@ fn draw_candle(
         Color::RED
     };
     let pos = Vector2::new(idx_pos, convert_coords_y(start_pos.y, step_y, max_y, max));
-    let size = Vector2::new(CANDLE_W, (max - min) * step_y);
+    let size = Vector2::new(coords.candle_w, (max - min) * step_y);
     d.draw_rectangle_v(pos, size, color);
     candle.position_x = Some(pos.x);
     candle.position_y = Some(pos.y);
     let high = Vector2::new(
-        idx_pos + CANDLE_W / 2.0,
+        idx_pos + coords.candle_w / 2.0,
         convert_coords_y(start_pos.y, step_y, max_y, candle.high),
     );
     let low = Vector2::new(
-        idx_pos + CANDLE_W / 2.0,
+        idx_pos + coords.candle_w / 2.0,
         convert_coords_y(start_pos.y, step_y, max_y, candle.low),
     );
     d.draw_line_v(high, low, color);
@@ -586,75 +836,6 @@ fn draw_frames_h1(
     }
 }
 
-#[allow(dead_code)]
-async fn draw_ui<'a>(
-    d: &mut RaylibDrawHandle<'a>,
-    ui: &mut UiElements<'a>,
-    pool: &'a PgPool,
-    candles: &mut Vec<Candle>,
-    coords: &mut DrawCoords,
-    begin: NaiveDateTime,
-    end: NaiveDateTime,
-    frame: &Frame,
-) {
-    if ui.securities_edit {
-        d.gui_lock();
-    }
-
-    d.draw_text_ex(
-        d.get_font_default(),
-        "BEGIN",
-        Vector2::new(25.0, 90.0),
-        10.0,
-        1.0,
-        Color::WHEAT,
-    );
-    if d.gui_text_box(
-        Rectangle::new(25.0, 100.0, 125.0, 30.0),
-        &mut ui.begin_str,
-        ui.begin_edit,
-    ) {
-        ui.begin_edit = !ui.begin_edit;
-    }
-
-    d.draw_text_ex(
-        d.get_font_default(),
-        "END",
-        Vector2::new(25.0, 135.0),
-        10.0,
-        1.0,
-        Color::WHEAT,
-    );
-    if d.gui_text_box(
-        Rectangle::new(25.0, 145.0, 125.0, 30.0),
-        &mut ui.end_str,
-        ui.end_edit,
-    ) {
-        ui.end_edit = !ui.end_edit;
-    }
-
-    d.gui_unlock();
-    d.gui_set_style(
-        GuiControl::DROPDOWNBOX,
-        TEXT_ALIGNMENT,
-        TEXT_ALIGN_CENTER as i32,
-    );
-    if d.gui_dropdown_box(
-        Rectangle::new(25.0, 25.0, 125.0, 30.0),
-        ui.securities,
-        &mut ui.securities_active,
-        ui.securities_edit,
-    ) {
-        ui.securities_edit = !ui.securities_edit;
-        if ui.secs[ui.securities_active as usize] != ui.selected_security {
-            ui.selected_security = ui.secs[ui.securities_active as usize];
-            (*candles, *coords) = fetch_data(pool, ui.selected_security, begin, end, frame)
-                .await
-                .unwrap();
-        }
-    }
-}
-
 fn draw_datepicker(
     d: &mut RaylibDrawHandle,
     position: Vector2,
@@ -672,6 +853,23 @@ fn draw_datepicker(
         0.0,
         Color::WHEAT,
     );
+    // стрелки вверх/вниз листают дни
+    if *ui_edit {
+        let days = if d.is_key_pressed(KeyboardKey::KEY_UP) {
+            1
+        } else if d.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            -1
+        } else {
+            0
+        };
+        if days != 0
+            && let Some(value) = ui_str
+                .get(..19)
+                .and_then(|a| NaiveDateTime::parse_from_str(a, DATE_TIME_FMT).ok())
+        {
+            *ui_str = format!("{} ", (value + Duration::days(days)).format(DATE_TIME_FMT));
+        }
+    }
     if d.gui_text_box(
         Rectangle::new(position.x, position.y + 10.0, 186.0, 30.0),
         ui_str,
@@ -719,24 +917,12 @@ fn draw_trades(
     font: &Font,
     trades: &Vec<TradeView>,
     coords: &DrawCoords,
+    area: &Rectangle,
     frame: &Frame,
 ) {
-    let end_y = coords.end_pos.y + TRADES_DELTA_Y;
-    let start_y = coords.start_pos.y + TRADES_DELTA_Y;
-    let mut min_y = i64::MAX;
-    let mut max_y = 0_i64;
-
-    for trade in trades {
-        let min = i64::min(trade.quantity_buy, trade.quantity_sell);
-        let max = i64::max(trade.quantity_buy, trade.quantity_sell);
-
-        if min < min_y {
-            min_y = min;
-        }
-        if max > max_y {
-            max_y = max;
-        }
-    }
+    let end_y = area.y + area.height;
+    let start_y = area.y;
+    let (min_y, max_y) = trades_range(trades);
 
     // draw y-axis
     d.draw_line_v(
@@ -803,34 +989,35 @@ fn draw_trades(
             );
         }
 
-        right += CANDLE_W;
-        left -= CANDLE_W;
+        right += coords.candle_w;
+        left -= coords.candle_w;
         i += 1;
     }
 
-    let y = coords.end_pos.y + TRADES_DELTA_Y;
+    let y = end_y;
     let mut day: u32 = 0;
     let mut month: u32 = 0;
 
     let step_y = (end_y - start_y) / (max_y - min_y) as f32;
     for (i, trade) in trades.into_iter().enumerate() {
-        let x = coords.start_pos.x + (i as f32 * CANDLE_W);
+        let x = coords.start_pos.x + (i as f32 * coords.candle_w);
+        let candle_w = coords.candle_w;
 
         // buy
         let position = Vector2::new(
-            x + CANDLE_W,
+            x + candle_w,
             convert_coords_y(start_y, step_y, max_y as f32, trade.quantity_buy as f32),
         );
-        let size = Vector2::new(CANDLE_W / 2.0, trade.quantity_buy as f32 * step_y);
+        let size = Vector2::new(candle_w / 2.0, trade.quantity_buy as f32 * step_y);
         let color = Color::GREEN;
         d.draw_rectangle_v(position, size, color);
 
         // sell
         let position = Vector2::new(
-            x + CANDLE_W + CANDLE_W / 2.0,
+            x + candle_w + candle_w / 2.0,
             convert_coords_y(start_y, step_y, max_y as f32, trade.quantity_sell as f32),
         );
-        let size = Vector2::new(CANDLE_W / 2.0, trade.quantity_sell as f32 * step_y);
+        let size = Vector2::new(candle_w / 2.0, trade.quantity_sell as f32 * step_y);
         let color = Color::RED;
         d.draw_rectangle_v(position, size, color);
 
@@ -846,40 +1033,47 @@ fn draw_trades(
     }
 }
 
-fn draw_arrow(d: &mut RaylibDrawHandle, current_candle: &mut Candle) {
-    if let Some(x) = current_candle.position_x {
-        if let Some(y) = current_candle.position_y {
-            let diff_x = 0.8;
-            let diff_y = 15.0;
-            let start_pos = Vector2::new(x + CANDLE_W / 2.0 - 1.5, 4.0);
-            let end_pos = Vector2::new(x + CANDLE_W / 2.0 - diff_x, y - diff_y);
-            let thick = 2.0;
-
-            // main
-            d.draw_line_ex(start_pos, end_pos, thick, Color::WHEAT);
-
-            // left
-            let start_pos = Vector2::new(x + CANDLE_W / 2.0 - 3.0, y - 22.0);
-            let end_pos = Vector2::new(x + CANDLE_W / 2.0 - diff_x, y - diff_y);
-            d.draw_line_ex(start_pos, end_pos, thick, Color::WHEAT);
-
-            // right
-            let start_pos = Vector2::new(x + CANDLE_W / 2.0 + 1.0, y - 22.0);
-            let end_pos = Vector2::new(x + CANDLE_W / 2.0 - diff_x, y - diff_y);
-            d.draw_line_ex(start_pos, end_pos, thick, Color::WHEAT);
+/// Min and max of buy/sell quantities, the scale of the trades panel
+fn trades_range(trades: &[TradeView]) -> (i64, i64) {
+    let mut min_y = i64::MAX;
+    let mut max_y = 0_i64;
+
+    for trade in trades {
+        let min = i64::min(trade.quantity_buy, trade.quantity_sell);
+        let max = i64::max(trade.quantity_buy, trade.quantity_sell);
+
+        if min < min_y {
+            min_y = min;
+        }
+        if max > max_y {
+            max_y = max;
         }
     }
+
+    (min_y, max_y)
 }
 
-fn draw_info(d: &mut RaylibDrawHandle, coords: &DrawCoords, font: &Font, info: &str) {
-    d.draw_text_ex(
-        font,
-        info,
-        Vector2::new(25.0, coords.start_pos.y + 220.0),
-        15.0,
-        0.0,
-        Color::WHEAT,
-    );
+/// Arrow pointing at `position`, from below when `up`
+fn draw_arrow(d: &mut RaylibDrawHandle, position: Vector2, up: bool, color: Color) {
+    let direction = if up { 1.0 } else { -1.0 };
+    let tip = Vector2::new(position.x, position.y + 2.0 * direction);
+    let thick = 2.0;
+
+    // main
+    let end_pos = Vector2::new(tip.x, tip.y + 16.0 * direction);
+    d.draw_line_ex(tip, end_pos, thick, color);
+
+    // left
+    let end_pos = Vector2::new(tip.x - 4.0, tip.y + 6.0 * direction);
+    d.draw_line_ex(tip, end_pos, thick, color);
+
+    // right
+    let end_pos = Vector2::new(tip.x + 4.0, tip.y + 6.0 * direction);
+    d.draw_line_ex(tip, end_pos, thick, color);
+}
+
+fn draw_info(d: &mut RaylibDrawHandle, position: Vector2, font: &Font, info: &str) {
+    d.draw_text_ex(font, info, position, 15.0, 0.0, Color::WHEAT);
 }
 
 fn mouse_click(
@@ -898,7 +1092,7 @@ fn mouse_click(
         {
             for candle in candles {
                 if mouse_position.x >= candle.position_x.unwrap_or(0.0)
-                    && mouse_position.x <= candle.position_x.unwrap_or(0.0) + CANDLE_W
+                    && mouse_position.x <= candle.position_x.unwrap_or(0.0) + coords.candle_w
                 {
                     *current_candle = candle.clone();
                     *info = current_candle.to_info();
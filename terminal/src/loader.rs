@@ -0,0 +1,292 @@
+use crate::chart::frame;
+use crate::viewport::{Side, Viewport, load_chunk};
+use app::db::pg;
+use app::models::common::{Attempt, Candle, Drawing, Frame, Operation, Tick, TradeView};
+use app::strategy::backtest::{self, BacktestResult};
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+use sqlx::types::Uuid;
+use std::collections::HashMap;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender, error::TryRecvError};
+use tokio::task::AbortHandle;
+
+/// What a request loads, a new request for the same target cancels the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// new charts of the split view
+    Add,
+    /// candles of the chart after a change of its security, frame or the range
+    Chart(usize),
+    /// next chunk of the history of the chart
+    More(usize),
+    Operations,
+    /// drawings of the security with this index
+    Drawings(i32),
+    /// save or removal of the drawing
+    Drawing(Uuid),
+    /// trades of the security with this index for the replay
+    Ticks(i32),
+    /// save of the paper trades, by the id of the first attempt
+    Paper(Uuid),
+    /// latest attempts of the dropdown
+    Attempts,
+}
+
+pub enum Request {
+    /// up to `count` charts, one for each of the first `candidates` that have data
+    Charts {
+        count: usize,
+        candidates: Vec<(i32, String)>,
+        frame_active: i32,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+    },
+    Chunk {
+        security: String,
+        frame: Frame,
+        side: Side,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+    },
+    Operations(Uuid),
+    Drawings(String),
+    SaveDrawing(Drawing),
+    RemoveDrawing(Uuid),
+    Ticks {
+        security: String,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+    },
+    /// results of the paper trading with their frames
+    SavePaper(Vec<(BacktestResult, Frame)>),
+    /// up to `limit` latest attempts
+    Attempts(i32),
+    Cancel,
+}
+
+pub enum Response {
+    Charts {
+        frame_active: i32,
+        /// security_active, security and the viewport of every loaded chart
+        charts: Vec<(i32, String, Viewport)>,
+    },
+    Chunk {
+        side: Side,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+        candles: Vec<Candle>,
+        trades: Vec<TradeView>,
+    },
+    Operations(Vec<Operation>),
+    Drawings {
+        security: String,
+        drawings: Vec<Drawing>,
+    },
+    Ticks {
+        security: String,
+        ticks: Vec<Tick>,
+    },
+    /// attempts saved from the paper trading or the latest ones
+    Attempts(Vec<Attempt>),
+    Done,
+}
+
+/// Render loop side of the background task that runs the queries,
+/// so the window keeps drawing while Postgres answers
+pub struct Loader {
+    requests: UnboundedSender<(Target, u64, Request)>,
+    responses: UnboundedReceiver<(Target, u64, Result<Response, String>)>,
+    /// id of the last request of every target that is not answered yet
+    pending: HashMap<Target, u64>,
+    last_id: u64,
+}
+
+impl Loader {
+    pub fn new(pool: &PgPool) -> Self {
+        let (requests, requests_rx) = mpsc::unbounded_channel();
+        let (responses_tx, responses) = mpsc::unbounded_channel();
+        tokio::spawn(run(pool.clone(), requests_rx, responses_tx));
+        Self {
+            requests,
+            responses,
+            pending: HashMap::new(),
+            last_id: 0,
+        }
+    }
+
+    pub fn send(&mut self, target: Target, request: Request) {
+        self.last_id += 1;
+        self.pending.insert(target, self.last_id);
+        self.requests
+            .send((target, self.last_id, request))
+            .expect("loader task stopped");
+    }
+
+    /// Drops the request of the target, its answer is ignored even if already sent
+    pub fn cancel(&mut self, target: Target) {
+        if self.pending.remove(&target).is_some() {
+            self.requests
+                .send((target, 0, Request::Cancel))
+                .expect("loader task stopped");
+        }
+    }
+
+    pub fn is_loading(&self, target: Target) -> bool {
+        self.pending.contains_key(&target)
+    }
+
+    /// Answers received since the last call, without the ones of cancelled
+    /// or replaced requests
+    pub fn poll(&mut self) -> Vec<(Target, Result<Response, String>)> {
+        let mut result = vec![];
+        loop {
+            match self.responses.try_recv() {
+                Ok((target, id, response)) => {
+                    if self.pending.get(&target) == Some(&id) {
+                        self.pending.remove(&target);
+                        result.push((target, response));
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => panic!("loader task stopped"),
+            }
+        }
+        result
+    }
+
+    /// Waits for the answers of the pending requests of the matching targets,
+    /// on exit the runtime would drop them unfinished
+    pub async fn wait(
+        &mut self,
+        matches: impl Fn(&Target) -> bool,
+    ) -> Vec<(Target, Result<Response, String>)> {
+        let mut result = vec![];
+        while self.pending.keys().any(&matches) {
+            let Some((target, id, response)) = self.responses.recv().await else {
+                break;
+            };
+            if self.pending.get(&target) == Some(&id) {
+                self.pending.remove(&target);
+                result.push((target, response));
+            }
+        }
+        result
+    }
+}
+
+/// Runs every request in its own task, aborting the previous one of the same target
+async fn run(
+    pool: PgPool,
+    mut requests: UnboundedReceiver<(Target, u64, Request)>,
+    responses: UnboundedSender<(Target, u64, Result<Response, String>)>,
+) {
+    let mut jobs: HashMap<Target, AbortHandle> = HashMap::new();
+    while let Some((target, id, request)) = requests.recv().await {
+        if let Some(job) = jobs.remove(&target) {
+            job.abort();
+        }
+        if let Request::Cancel = request {
+            continue;
+        }
+        let job = tokio::spawn(load(pool.clone(), request));
+        jobs.retain(|_, a| !a.is_finished());
+        jobs.insert(target, job.abort_handle());
+
+        // a panic of the query is an error on the screen, not an endless loading
+        let responses = responses.clone();
+        tokio::spawn(async move {
+            let response = match job.await {
+                Ok(response) => response,
+                Err(e) if e.is_cancelled() => return,
+                Err(e) => Err(format!("failed to load: {e}")),
+            };
+            // окно уже закрыто
+            let _ = responses.send((target, id, response));
+        });
+    }
+}
+
+async fn load(pool: PgPool, request: Request) -> Result<Response, String> {
+    match request {
+        Request::Charts {
+            count,
+            candidates,
+            frame_active,
+            begin,
+            end,
+        } => {
+            let frame = frame(frame_active);
+            let single = candidates.len() == 1;
+            let mut charts = vec![];
+            for (security_active, security) in candidates {
+                if charts.len() == count {
+                    break;
+                }
+                if let Some(viewport) = Viewport::load(&pool, &security, begin, end, &frame).await {
+                    charts.push((security_active, security, viewport));
+                } else if single {
+                    return Err(format!(
+                        "Has no data for: {}, {} - {}",
+                        security, begin, end
+                    ));
+                }
+            }
+            if charts.is_empty() {
+                return Err(format!("Has no data for: {} - {}", begin, end));
+            }
+            Ok(Response::Charts {
+                frame_active,
+                charts,
+            })
+        }
+        Request::Chunk {
+            security,
+            frame,
+            side,
+            begin,
+            end,
+        } => {
+            let (candles, trades) = load_chunk(&pool, &security, begin, end, &frame).await;
+            Ok(Response::Chunk {
+                side,
+                begin,
+                end,
+                candles,
+                trades,
+            })
+        }
+        Request::Operations(attempt) => Ok(Response::Operations(
+            pg::get_operations(&pool, attempt).await,
+        )),
+        Request::Drawings(security) => {
+            let drawings = pg::get_drawings(&pool, &security).await;
+            Ok(Response::Drawings { security, drawings })
+        }
+        Request::SaveDrawing(drawing) => {
+            pg::save_drawing(&pool, &drawing).await;
+            Ok(Response::Done)
+        }
+        Request::RemoveDrawing(id) => {
+            pg::remove_drawing(&pool, id).await;
+            Ok(Response::Done)
+        }
+        Request::Ticks {
+            security,
+            begin,
+            end,
+        } => {
+            let ticks = pg::get_ticks(&pool, &security, begin, end).await;
+            Ok(Response::Ticks { security, ticks })
+        }
+        Request::SavePaper(results) => {
+            let mut attempts = vec![];
+            for (result, frame) in results {
+                backtest::save_result(&pool, &result, &frame).await;
+                attempts.push(result.attempt);
+            }
+            Ok(Response::Attempts(attempts))
+        }
+        Request::Attempts(limit) => Ok(Response::Attempts(pg::get_attempts(&pool, limit).await)),
+        Request::Cancel => unreachable!(),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{DrawCoords, candle_x, convert_coords_y, draw_arrow};
+use app::models::common::{Attempt, Candle, Operation, OperationType};
+use raylib::prelude::*;
+
+/// Distance to a marker in pixels to show its round trip
+const HOVER_DISTANCE: f32 = 8.0;
+
+/// Entry and exit of one position on the chart
+struct Trip {
+    entry: Option<Vector2>,
+    exit: Vector2,
+    pnl: f32,
+    percent: f32,
+}
+
+/// Latest attempts shown in the dropdown
+pub const ATTEMPTS_LIMIT: i32 = 50;
+
+/// Items of the attempt dropdown, the first one hides operations
+pub fn attempts_list(attempts: &[Attempt]) -> String {
+    let mut items = vec![String::from("NONE")];
+    for attempt in attempts {
+        items.push(format!(
+            "{} {}",
+            &attempt.id.to_string()[..8],
+            attempt.strategy
+        ));
+    }
+    items.join(";")
+}
+
+/// Markers of the operations of `security` on visible candles,
+/// round trips are connected and PnL is shown on hover
+pub fn draw_operations(
+    d: &mut RaylibDrawHandle,
+    font: &Font,
+    coords: &DrawCoords,
+    candles: &[Candle],
+    operations: &[Operation],
+    security: &str,
+) {
+    let mut trips: Vec<Trip> = vec![];
+    let mut entry: Option<(&Operation, Option<Vector2>)> = None;
+    for operation in operations.iter().filter(|a| a.security == security) {
+        let position = candle_index(candles, operation).map(|i| {
+            Vector2::new(
+                candle_x(coords, i),
+                convert_coords_y(
+                    coords.start_pos.y,
+                    coords.step_y,
+                    coords.max_y,
+                    operation.price,
+                ),
+            )
+        });
+        match operation.operation_type {
+            OperationType::Buy | OperationType::Short => {
+                entry = Some((operation, position));
+                if let Some(position) = position {
+                    let up = matches!(operation.operation_type, OperationType::Buy);
+                    draw_arrow(d, position, up, Color::GREEN);
+                }
+            }
+            OperationType::Sold | OperationType::Cover => {
+                let position = match position {
+                    Some(position) => position,
+                    None => continue,
+                };
+                let up = matches!(operation.operation_type, OperationType::Cover);
+                draw_arrow(d, position, up, Color::RED);
+                if let Some((open, open_position)) = entry {
+                    trips.push(trip(open, open_position, operation, position));
+                }
+            }
+        }
+    }
+
+    let mouse = d.get_mouse_position();
+    for trip in trips.iter() {
+        let color = if trip.pnl >= 0.0 {
+            Color::GREEN
+        } else {
+            Color::RED
+        };
+        if let Some(entry) = trip.entry {
+            d.draw_line_v(entry, trip.exit, color.alpha(0.6));
+        }
+        let hover = [Some(trip.exit), trip.entry]
+            .into_iter()
+            .flatten()
+            .any(|a| a.distance_to(mouse) <= HOVER_DISTANCE);
+        if hover {
+            d.draw_text_ex(
+                font,
+                &format!("PnL: {:.2} ({:.2}%)", trip.pnl, trip.percent),
+                Vector2::new(mouse.x + 12.0, mouse.y - 18.0),
+                15.0,
+                0.0,
+                color,
+            );
+        }
+    }
+}
+
+/// Result of the exit against the entry, commission of both included
+fn trip(
+    entry: &Operation,
+    entry_position: Option<Vector2>,
+    exit: &Operation,
+    exit_position: Vector2,
+) -> Trip {
+    let side = match entry.operation_type {
+        OperationType::Short => -1.0,
+        _ => 1.0,
+    };
+    let count = exit.count as f32;
+    let entry_commission = entry.commission * count / entry.count.max(1) as f32;
+    let pnl = side * (exit.price - entry.price) * count - exit.commission - entry_commission;
+    let cost = entry.price * count;
+    Trip {
+        entry: entry_position,
+        exit: exit_position,
+        pnl,
+        percent: if cost > 0.0 { pnl / cost * 100.0 } else { 0.0 },
+    }
+}
+
+/// Candle that contains the time of the operation
+fn candle_index(candles: &[Candle], operation: &Operation) -> Option<usize> {
+    let time = operation.time_at;
+    let i = candles.partition_point(|a| a.begin <= time);
+    if i == 0 || time > candles[i - 1].end {
+        return None;
+    }
+    Some(i - 1)
+}
@@ -0,0 +1,259 @@
+use crate::{DrawCoords, candle_x, convert_coords_y, draw_axis};
+use app::indicators::{Bands, Bollinger, Ema, Macd, MacdValue, Rsi, Sma, Vwap, batch};
+use app::models::common::{Candle, TradeView};
+use raylib::prelude::*;
+use std::ops::Range;
+
+const PERIOD: usize = 20;
+pub const PANELS: &str = "OFF;RSI;MACD;DELTA";
+
+/// Indicators selected in the left-side UI
+#[derive(Default)]
+pub struct Overlays {
+    pub sma: bool,
+    pub ema: bool,
+    pub bollinger: bool,
+    pub vwap: bool,
+    /// index in `PANELS`
+    pub panel: i32,
+}
+
+/// Values of every indicator, one per candle, computed over the whole loaded
+/// history so they don't restart at the left edge of the view, and only
+/// selected for drawing every frame
+#[derive(Default)]
+pub struct Indicators {
+    sma: Vec<Option<f32>>,
+    ema: Vec<Option<f32>>,
+    bollinger: Vec<Option<Bands>>,
+    vwap: Vec<Option<f32>>,
+    rsi: Vec<Option<f32>>,
+    macd: Vec<Option<MacdValue>>,
+    /// buy minus sell volume, the trades are aligned to the candles by period
+    /// in `load_chunk`
+    delta: Vec<Option<f32>>,
+}
+
+impl Indicators {
+    pub fn new(candles: &[Candle], trades: &[TradeView]) -> Self {
+        Self {
+            sma: batch(Sma::new(PERIOD), candles),
+            ema: batch(Ema::new(PERIOD), candles),
+            bollinger: batch(Bollinger::new(PERIOD, 2.0), candles),
+            vwap: batch(Vwap::default(), candles),
+            rsi: batch(Rsi::new(14), candles),
+            macd: batch(Macd::default(), candles),
+            delta: delta(trades, candles.len()),
+        }
+    }
+
+    /// Values of the `range` of the history for the visible `trades`, the forming
+    /// candle of the replay after the range has only its delta
+    pub fn slice(&self, range: Range<usize>, trades: &[TradeView]) -> Self {
+        let len = trades.len();
+        Self {
+            sma: part(&self.sma, &range, len),
+            ema: part(&self.ema, &range, len),
+            bollinger: part(&self.bollinger, &range, len),
+            vwap: part(&self.vwap, &range, len),
+            rsi: part(&self.rsi, &range, len),
+            macd: part(&self.macd, &range, len),
+            delta: delta(trades, len),
+        }
+    }
+}
+
+fn delta(trades: &[TradeView], len: usize) -> Vec<Option<f32>> {
+    trades
+        .iter()
+        .take(len)
+        .map(|a| Some((a.quantity_buy - a.quantity_sell) as f32))
+        .collect()
+}
+
+/// `len` values from the start of `range`, `None` past the series
+fn part<T: Copy>(values: &[Option<T>], range: &Range<usize>, len: usize) -> Vec<Option<T>> {
+    let mut result = values
+        .get(range.clone())
+        .map_or_else(Vec::new, |a| a.to_vec());
+    result.resize(len, None);
+    result
+}
+
+pub fn draw_overlays_ui(d: &mut RaylibDrawHandle, overlays: &mut Overlays, position: Vector2) {
+    let size = 15.0;
+    let items = [
+        ("SMA 20", &mut overlays.sma),
+        ("EMA 20", &mut overlays.ema),
+        ("BOLL 20", &mut overlays.bollinger),
+        ("VWAP", &mut overlays.vwap),
+    ];
+    for (i, (label, checked)) in items.into_iter().enumerate() {
+        let bounds = Rectangle::new(position.x, position.y + i as f32 * 22.0, size, size);
+        d.gui_check_box(bounds, label, checked);
+    }
+    let bounds = Rectangle::new(position.x, position.y + 4.0 * 22.0 + 5.0, 44.0, 25.0);
+    d.gui_toggle_group(bounds, PANELS, &mut overlays.panel);
+}
+
+/// Lines over the candle area
+pub fn draw_overlays(
+    d: &mut RaylibDrawHandle,
+    coords: &DrawCoords,
+    indicators: &Indicators,
+    overlays: &Overlays,
+) {
+    for (values, color) in overlay_lines(indicators, overlays) {
+        draw_line(d, coords, &values, color);
+    }
+}
+
+/// Values of the selected indicators, one per candle, with their colors
+pub fn overlay_lines(
+    indicators: &Indicators,
+    overlays: &Overlays,
+) -> Vec<(Vec<Option<f32>>, Color)> {
+    let mut lines = vec![];
+    if overlays.sma {
+        lines.push((indicators.sma.clone(), Color::YELLOW));
+    }
+    if overlays.ema {
+        lines.push((indicators.ema.clone(), Color::SKYBLUE));
+    }
+    if overlays.bollinger {
+        let bands = &indicators.bollinger;
+        let color = Color::VIOLET;
+        lines.push((map(bands, |a| a.upper), color));
+        lines.push((map(bands, |a| a.middle), color.alpha(0.5)));
+        lines.push((map(bands, |a| a.lower), color));
+    }
+    if overlays.vwap {
+        lines.push((indicators.vwap.clone(), Color::ORANGE));
+    }
+    lines
+}
+
+/// Indicator sub-panel under the trades, scaled like the candle area
+pub fn draw_panel(
+    d: &mut RaylibDrawHandle,
+    font: &Font,
+    coords: &DrawCoords,
+    area: &Rectangle,
+    indicators: &Indicators,
+    overlays: &Overlays,
+) {
+    let panel = PANELS
+        .split(";")
+        .nth(overlays.panel as usize)
+        .unwrap_or("OFF");
+    match panel {
+        "RSI" => {
+            let values = &indicators.rsi;
+            let coords = panel_coords(coords, area, 0.0, 100.0);
+            draw_axis(d, font, &coords);
+            for level in [30.0, 70.0] {
+                let y = convert_coords_y(coords.start_pos.y, coords.step_y, coords.max_y, level);
+                d.draw_line_v(
+                    Vector2::new(coords.start_pos.x, y),
+                    Vector2::new(coords.end_pos.x, y),
+                    Color::GRAY,
+                );
+            }
+            draw_line(d, &coords, values, Color::YELLOW);
+        }
+        "MACD" => {
+            let values = &indicators.macd;
+            let all = values
+                .iter()
+                .flatten()
+                .flat_map(|a| [a.macd, a.signal, a.histogram]);
+            let coords = match bounds(all) {
+                Some((min, max)) => panel_coords(coords, area, min, max),
+                None => return,
+            };
+            draw_axis(d, font, &coords);
+            draw_bars(d, &coords, &map(values, |a| a.histogram));
+            draw_line(d, &coords, &map(values, |a| a.macd), Color::SKYBLUE);
+            draw_line(d, &coords, &map(values, |a| a.signal), Color::ORANGE);
+        }
+        "DELTA" => {
+            let values = &indicators.delta;
+            let coords = match bounds(values.iter().flatten().copied()) {
+                Some((min, max)) => panel_coords(coords, area, min, max),
+                None => return,
+            };
+            draw_axis(d, font, &coords);
+            draw_bars(d, &coords, values);
+        }
+        _ => {}
+    }
+}
+
+/// Coords of the sub-panel in `area` with the x-axis of the candle area
+fn panel_coords(coords: &DrawCoords, area: &Rectangle, min_y: f32, max_y: f32) -> DrawCoords {
+    let start_pos = Vector2::new(coords.start_pos.x, area.y);
+    let end_pos = Vector2::new(coords.end_pos.x, area.y + area.height);
+    let (min_y, max_y) = if max_y > min_y {
+        (min_y, max_y)
+    } else {
+        (min_y - 1.0, max_y + 1.0)
+    };
+    DrawCoords {
+        start_pos,
+        end_pos,
+        step_y: (end_pos.y - start_pos.y) / (max_y - min_y),
+        min_y,
+        max_y,
+        candle_w: coords.candle_w,
+    }
+}
+
+fn bounds(values: impl Iterator<Item = f32>) -> Option<(f32, f32)> {
+    values.fold(None, |acc, a| match acc {
+        Some((min, max)) => Some((f32::min(min, a), f32::max(max, a))),
+        None => Some((a, a)),
+    })
+}
+
+fn map<T>(values: &[Option<T>], f: impl Fn(&T) -> f32) -> Vec<Option<f32>> {
+    values.iter().map(|a| a.as_ref().map(&f)).collect()
+}
+
+fn draw_line(d: &mut RaylibDrawHandle, coords: &DrawCoords, values: &[Option<f32>], color: Color) {
+    let y = |value: f32| {
+        convert_coords_y(coords.start_pos.y, coords.step_y, coords.max_y, value)
+            .clamp(coords.start_pos.y, coords.end_pos.y)
+    };
+    let mut prev: Option<Vector2> = None;
+    for (i, value) in values.iter().enumerate() {
+        let point = value.map(|a| Vector2::new(candle_x(coords, i), y(a)));
+        if let (Some(prev), Some(point)) = (prev, point) {
+            d.draw_line_v(prev, point, color);
+        }
+        prev = point;
+    }
+}
+
+/// Bars from zero, green above and red below
+fn draw_bars(d: &mut RaylibDrawHandle, coords: &DrawCoords, values: &[Option<f32>]) {
+    let zero = convert_coords_y(coords.start_pos.y, coords.step_y, coords.max_y, 0.0)
+        .clamp(coords.start_pos.y, coords.end_pos.y);
+    for (i, value) in values.iter().enumerate() {
+        let value = match value {
+            Some(value) => *value,
+            None => continue,
+        };
+        let y = convert_coords_y(coords.start_pos.y, coords.step_y, coords.max_y, value);
+        let color = if value >= 0.0 {
+            Color::GREEN
+        } else {
+            Color::RED
+        };
+        let x = candle_x(coords, i) - coords.candle_w / 4.0;
+        d.draw_rectangle_v(
+            Vector2::new(x, f32::min(y, zero)),
+            Vector2::new(coords.candle_w / 2.0, (y - zero).abs()),
+            color,
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use crate::chart::Chart;
+use crate::commands::Command;
+use crate::loader::{Loader, Request, Target};
+use crate::replay::Replay;
+use app::models::common::{Attempt, Frame, Operation, Packet, Params};
+use app::strategy::backtest::{
+    self, BacktestResult, COMMISSION, MIN_COUNT, START_BALANCE, VERSION, execute,
+};
+use app::strategy::base::Signal;
+use chrono::{Local, NaiveDateTime};
+use raylib::prelude::*;
+use sqlx::PgPool;
+use sqlx::types::Uuid;
+
+/// Strategy name of the saved attempts
+const STRATEGY: &str = "manual";
+
+/// Paper trading of one security, saved as its own attempt like a backtest run
+struct Session {
+    packet: Packet,
+    attempt: Attempt,
+    frame: Frame,
+    operations: Vec<Operation>,
+    /// balance plus the open position after every order and step of the replay
+    equity: Vec<(NaiveDateTime, f32)>,
+}
+
+impl Session {
+    fn new(chart: &Chart, time: NaiveDateTime) -> Self {
+        // тот же пакет, что и у бэктеста, чтобы попытки были сравнимы
+        let packet = Packet::new(&chart.security, MIN_COUNT, START_BALANCE);
+        let attempt = Attempt {
+            id: Uuid::new_v4(),
+            created_at: Local::now().naive_local(),
+            strategy: STRATEGY.to_string(),
+            securities: vec![chart.security.clone()],
+            begin: Some(time),
+            end: None,
+            frame: chart.frame().to_string(),
+            version: VERSION.to_string(),
+            profit: 0.0,
+            commission: COMMISSION,
+            borrow_fee: packet.borrow_fee,
+            fill: packet.fill.to_string(),
+            params: Params::new(),
+            metrics: serde_json::Value::Null,
+        };
+        Self {
+            packet,
+            attempt,
+            frame: chart.frame(),
+            operations: vec![],
+            equity: vec![(time, START_BALANCE)],
+        }
+    }
+}
+
+/// Manual orders at the time of the replay, filled at the last trade in the
+/// trade mode or at the close of the last shown candle of the active chart
+#[derive(Default)]
+pub struct Paper {
+    sessions: Vec<Session>,
+}
+
+impl Paper {
+    /// Operations not saved yet, shown on the charts with the ones of the attempt
+    pub fn operations(&self) -> impl Iterator<Item = &Operation> {
+        self.sessions.iter().flat_map(|a| a.operations.iter())
+    }
+
+    /// Paper commands, returns the message for the info line. The trades are saved
+    /// when the replay stops
+    pub fn run(
+        &mut self,
+        command: Command,
+        replay: &Replay,
+        charts: &[Chart],
+        active: usize,
+        loader: &mut Loader,
+    ) -> Option<String> {
+        let time = replay.time()?;
+        match command {
+            Command::PaperBuy | Command::PaperSell => {
+                let chart = charts.get(active)?;
+                let buy = command == Command::PaperBuy;
+                self.order(buy, chart, replay, time).err()
+            }
+            Command::PaperSave | Command::Replay => {
+                self.save(loader, time);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Marks the sessions at the price of the replay time after every step, like
+    /// the backtest marks its equity at every candle, so the saved report sees
+    /// the drawdown of an open position
+    pub fn update(&mut self, replay: &Replay, charts: &[Chart]) {
+        let Some(time) = replay.time() else {
+            return;
+        };
+        for session in &mut self.sessions {
+            if session.equity.last().is_some_and(|a| a.0 >= time) {
+                continue;
+            }
+            let Some(price) = charts
+                .iter()
+                .find(|a| a.security == session.packet.security)
+                .and_then(|a| price(a, replay))
+            else {
+                continue;
+            };
+            let packet = &session.packet;
+            session
+                .equity
+                .push((time, packet.balance + packet.purchased as f32 * price));
+        }
+    }
+
+    /// Buy opens a long or covers a short, sell opens a short or closes a long
+    fn order(
+        &mut self,
+        buy: bool,
+        chart: &Chart,
+        replay: &Replay,
+        time: NaiveDateTime,
+    ) -> Result<(), String> {
+        let price = match price(chart, replay) {
+            Some(price) => price,
+            None => return Err(String::from("Has no candles at the replay time")),
+        };
+        let i = match self
+            .sessions
+            .iter()
+            .position(|a| a.packet.security == chart.security)
+        {
+            Some(i) => i,
+            None => {
+                self.sessions.push(Session::new(chart, time));
+                self.sessions.len() - 1
+            }
+        };
+        let session = &mut self.sessions[i];
+        let signal = match (buy, session.packet.purchased.signum()) {
+            (true, 0) => Signal::Buy,
+            (true, -1) => Signal::Cover,
+            (false, 0) => Signal::Short,
+            (false, 1) => Signal::Sold,
+            (true, _) => return Err(String::from("Already long, sell to close")),
+            (false, _) => return Err(String::from("Already short, buy to cover")),
+        };
+
+        let count = session.operations.len();
+        execute(
+            &mut session.packet,
+            &session.attempt,
+            &[],
+            signal,
+            price,
+            (time, time),
+            &mut session.operations,
+        );
+        if session.operations.len() == count {
+            return Err(format!(
+                "Not enough balance for {} at {:.2}",
+                chart.security, price
+            ));
+        }
+        let packet = &session.packet;
+        session
+            .equity
+            .push((time, packet.balance + packet.purchased as f32 * price));
+        Ok(())
+    }
+
+    /// Saves the sessions with operations as attempts in the background, the next
+    /// order starts new ones
+    fn save(&mut self, loader: &mut Loader, end: NaiveDateTime) {
+        let results = self.results(end);
+        if let Some((first, _)) = results.first() {
+            loader.send(Target::Paper(first.attempt.id), Request::SavePaper(results));
+        }
+    }
+
+    /// Saves the trades left when the window is closed, the ones already handed
+    /// to the loader are waited for with `Loader::wait`
+    pub async fn save_on_exit(&mut self, pool: &PgPool, replay: &Replay) {
+        let Some(end) = replay.time() else {
+            return;
+        };
+        for (result, frame) in self.results(end) {
+            backtest::save_result(pool, &result, &frame).await;
+        }
+    }
+
+    fn results(&mut self, end: NaiveDateTime) -> Vec<(BacktestResult, Frame)> {
+        self.sessions
+            .drain(..)
+            .filter(|a| !a.operations.is_empty())
+            .map(|a| {
+                let result = BacktestResult {
+                    attempt: Attempt {
+                        end: Some(end),
+                        ..a.attempt
+                    },
+                    operations: a.operations,
+                    equity: a.equity,
+                    packets: vec![a.packet],
+                };
+                (result, a.frame)
+            })
+            .collect()
+    }
+
+    /// Buy, sell and save buttons with the position and PnL of the active chart,
+    /// only during the replay, returns the command of a pressed button
+    pub fn draw(
+        &self,
+        d: &mut RaylibDrawHandle,
+        font: &Font,
+        position: Vector2,
+        replay: &Replay,
+        chart: Option<&Chart>,
+    ) -> Option<Command> {
+        if !replay.enabled {
+            return None;
+        }
+        let mut command = None;
+        let buttons = [
+            ("BUY", Command::PaperBuy),
+            ("SELL", Command::PaperSell),
+            ("SAVE", Command::PaperSave),
+        ];
+        for (i, (label, a)) in buttons.into_iter().enumerate() {
+            let bounds = Rectangle::new(position.x + i as f32 * 55.0, position.y, 50.0, 30.0);
+            if d.gui_button(bounds, label) {
+                command = Some(a);
+            }
+        }
+
+        let Some(chart) = chart else {
+            return command;
+        };
+        let packet = self
+            .sessions
+            .iter()
+            .find(|a| a.packet.security == chart.security)
+            .map(|a| &a.packet);
+        let price = price(chart, replay).unwrap_or(0.0);
+        let (position_text, pnl, total) = match packet {
+            Some(packet) if packet.purchased != 0 => {
+                let side = if packet.purchased > 0 {
+                    "LONG"
+                } else {
+                    "SHORT"
+                };
+                let pnl = packet.purchased as f32 * (price - packet.entry_price);
+                let total = packet.balance + packet.purchased as f32 * price - START_BALANCE;
+                let text = format!(
+                    "{} {} @ {:.2}",
+                    side,
+                    packet.purchased.abs(),
+                    packet.entry_price
+                );
+                (text, pnl, total)
+            }
+            Some(packet) => (String::from("FLAT"), 0.0, packet.balance - START_BALANCE),
+            None => (String::from("FLAT"), 0.0, 0.0),
+        };
+        let lines = [
+            position_text,
+            format!("PNL {:+.2}  TOTAL {:+.2}", pnl, total),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            let position = Vector2::new(position.x, position.y + 37.0 + i as f32 * 20.0);
+            let color = match i {
+                1 if total > 0.0 => Color::GREEN,
+                1 if total < 0.0 => Color::RED,
+                _ => Color::WHEAT,
+            };
+            d.draw_text_ex(font, line, position, 15.0, 0.0, color);
+        }
+
+        command
+    }
+}
+
+/// Last trade of the security by the replay time when the trades are loaded,
+/// the close of the last shown candle otherwise
+fn price(chart: &Chart, replay: &Replay) -> Option<f32> {
+    replay
+        .last_price(&chart.security)
+        .or_else(|| chart.viewport.last_shown().map(|a| a.close))
+}
@@ -0,0 +1,181 @@
+use crate::{DATE_FMT, DATE_TIME_FMT, draw_datepicker};
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime};
+use raylib::prelude::*;
+
+/// Quick ranges, see `DateRange::preset`
+pub const PRESETS: [&str; 3] = ["TODAY", "5 SES", "MONTH"];
+const SESSIONS: usize = 5;
+
+/// Begin/end of the loaded candles with text inputs and presets,
+/// `end` is exclusive
+pub struct DateRange {
+    pub begin: NaiveDateTime,
+    pub end: NaiveDateTime,
+    begin_str: String,
+    begin_edit: bool,
+    end_str: String,
+    end_edit: bool,
+    /// trading dates, sorted
+    sessions: Vec<NaiveDate>,
+    pub error: Option<String>,
+}
+
+impl DateRange {
+    /// First session of `dates` from `get_start_info`
+    pub fn new(dates: &str) -> Self {
+        let mut sessions = dates
+            .split(";")
+            .filter_map(|a| NaiveDate::parse_from_str(a, DATE_FMT).ok())
+            .collect::<Vec<_>>();
+        sessions.sort();
+        let first = sessions
+            .first()
+            .copied()
+            .unwrap_or_else(|| Local::now().date_naive());
+        let begin = first.and_hms_opt(0, 0, 0).unwrap();
+
+        let mut range = Self {
+            begin,
+            end: begin,
+            begin_str: String::new(),
+            begin_edit: false,
+            end_str: String::new(),
+            end_edit: false,
+            sessions,
+            error: None,
+        };
+        range.set(begin, begin + Duration::days(1));
+        range
+    }
+
+    /// One of the text inputs is edited
+    pub fn is_editing(&self) -> bool {
+        self.begin_edit || self.end_edit
+    }
+
+    pub fn set(&mut self, begin: NaiveDateTime, end: NaiveDateTime) {
+        self.begin = begin;
+        self.end = end;
+        self.begin_str = format!("{} ", begin.format(DATE_TIME_FMT));
+        self.end_str = format!("{} ", end.format(DATE_TIME_FMT));
+        self.error = None;
+    }
+
+    /// One day range of the date
+    pub fn set_day(&mut self, date: NaiveDate) {
+        let begin = date.and_hms_opt(0, 0, 0).unwrap();
+        self.set(begin, begin + Duration::days(1));
+    }
+
+    /// One day range of the next trading date after the begin, or the previous one
+    /// if `step` is negative, returns false if there is none
+    pub fn step_session(&mut self, step: i32) -> bool {
+        let date = self.begin.date();
+        let session = if step > 0 {
+            self.sessions.iter().find(|a| **a > date)
+        } else {
+            self.sessions.iter().rev().find(|a| **a < date)
+        };
+        match session {
+            Some(session) => {
+                self.set_day(*session);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_preset(&mut self, i: usize) -> bool {
+        match self.preset(i) {
+            Some((begin, end)) => {
+                self.set(begin, end);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Inputs, presets and the last error, returns true when the range changed
+    pub fn draw(&mut self, d: &mut RaylibDrawHandle, font: &Font, position: Vector2) -> bool {
+        let (mut begin, mut end) = (self.begin, self.end);
+        let (begin_edit, end_edit) = (self.begin_edit, self.end_edit);
+        let mut changed = draw_datepicker(
+            d,
+            position,
+            &mut self.begin_str,
+            &mut self.begin_edit,
+            "BEGIN",
+            &mut begin,
+            font,
+        );
+        changed |= draw_datepicker(
+            d,
+            Vector2::new(position.x, position.y + 50.0),
+            &mut self.end_str,
+            &mut self.end_edit,
+            "END",
+            &mut end,
+            font,
+        );
+        let committed = (begin_edit && !self.begin_edit) || (end_edit && !self.end_edit);
+        if committed && !changed {
+            self.error = Some(String::from("expected YYYY-MM-DD HH:MM:SS"));
+        }
+
+        for (i, label) in PRESETS.iter().enumerate() {
+            let bounds =
+                Rectangle::new(position.x + i as f32 * 63.0, position.y + 95.0, 60.0, 25.0);
+            if d.gui_button(bounds, label)
+                && let Some(range) = self.preset(i)
+            {
+                (begin, end) = range;
+                changed = true;
+            }
+        }
+
+        if changed {
+            if begin < end {
+                self.set(begin, end);
+            } else {
+                self.set(self.begin, self.end);
+                self.error = Some(String::from("begin must be before end"));
+                changed = false;
+            }
+        }
+
+        if let Some(error) = &self.error {
+            d.draw_text_ex(
+                font,
+                error,
+                Vector2::new(position.x, position.y + 128.0),
+                15.0,
+                0.0,
+                Color::RED,
+            );
+        }
+
+        changed
+    }
+
+    /// Today, the last `SESSIONS` trading dates or the month of the last one
+    fn preset(&self, i: usize) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let (begin, end) = match i {
+            0 => {
+                let today = Local::now().date_naive();
+                (today, today + Duration::days(1))
+            }
+            1 => {
+                let from = self.sessions.len().saturating_sub(SESSIONS);
+                let first = *self.sessions.get(from)?;
+                let last = *self.sessions.last()?;
+                (first, last + Duration::days(1))
+            }
+            _ => {
+                let last = *self.sessions.last()?;
+                let first = last.with_day(1)?;
+                (first, first.checked_add_months(Months::new(1))?)
+            }
+        };
+        Some((begin.and_hms_opt(0, 0, 0)?, end.and_hms_opt(0, 0, 0)?))
+    }
+}
@@ -0,0 +1,269 @@
+use crate::DATE_TIME_FMT;
+use crate::chart::Chart;
+use crate::commands::Command;
+use crate::layout::Cell;
+use crate::loader::{Loader, Request, Target};
+use crate::range::DateRange;
+use app::models::common::{Operation, Tick};
+use chrono::{Duration, NaiveDateTime};
+use raylib::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+pub const MODES: &str = "CANDLES;TRADES";
+const SPEEDS: &str = "1;2;5;10;50";
+/// Steps per second of every speed of `SPEEDS`
+const STEPS: [f32; 5] = [1.0, 2.0, 5.0, 10.0, 50.0];
+/// Steps made in one frame at most, the rest is dropped after a freeze of the window
+const MAX_STEPS: usize = 10;
+
+/// Replay of the range: the charts show only what was known by `time`, which moves
+/// candle by candle of the active chart or trade by trade of its security,
+/// trades of the same second come together
+#[derive(Default)]
+pub struct Replay {
+    pub enabled: bool,
+    pub playing: bool,
+    /// index in `MODES`
+    pub mode: i32,
+    /// index in `SPEEDS`
+    pub speed: i32,
+    time: NaiveDateTime,
+    /// seconds since the last step
+    elapsed: f32,
+    /// trades of the securities of the charts, loaded in the trade mode
+    ticks: HashMap<String, Vec<Tick>>,
+    /// the charts hide the future
+    applied: bool,
+}
+
+impl Replay {
+    /// Everything made by this time is shown, `None` outside of the replay
+    pub fn time(&self) -> Option<NaiveDateTime> {
+        self.enabled.then_some(self.time)
+    }
+
+    /// Replay commands from the hotkeys, the palette or the buttons
+    pub fn run(
+        &mut self,
+        command: Command,
+        begin: NaiveDateTime,
+        charts: &[Chart],
+        active: usize,
+        loader: &Loader,
+    ) {
+        match command {
+            Command::Replay => {
+                self.enabled = !self.enabled;
+                if self.enabled {
+                    self.restart(begin, charts, active, loader);
+                }
+                self.playing = false;
+            }
+            Command::ReplayPlay if self.enabled => {
+                self.playing = !self.playing;
+                self.elapsed = 0.0;
+            }
+            Command::ReplayStep if self.enabled => {
+                self.playing = false;
+                self.step(charts, active, loader);
+            }
+            _ => {}
+        }
+    }
+
+    /// Back to the session open at `begin` with its first candle or trade
+    pub fn restart(
+        &mut self,
+        begin: NaiveDateTime,
+        charts: &[Chart],
+        active: usize,
+        loader: &Loader,
+    ) {
+        self.time = begin - Duration::seconds(1);
+        self.elapsed = 0.0;
+        self.playing = false;
+        self.step(charts, active, loader);
+    }
+
+    /// Moves the time to the end of the next candle of the active chart or to the
+    /// next trade of its security, returns false if there is none loaded
+    pub fn step(&mut self, charts: &[Chart], active: usize, loader: &Loader) -> bool {
+        let chart = match charts.get(active) {
+            Some(chart) if !loader.is_loading(Target::Chart(active)) => chart,
+            _ => return false,
+        };
+        let next = if self.mode == 0 {
+            let history = &chart.viewport.history;
+            let i = history.partition_point(|a| a.end <= self.time);
+            history.get(i).map(|a| a.end)
+        } else {
+            let ticks = self
+                .ticks
+                .get(&chart.security)
+                .map_or(&[][..], |a| a.as_slice());
+            let i = ticks.partition_point(|a| a.time <= self.time);
+            ticks.get(i).map(|a| a.time)
+        };
+        match next {
+            Some(time) => {
+                self.time = time;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Steps for the time of the frame while playing, pauses at the end of the data
+    pub fn update(&mut self, frame_time: f32, charts: &[Chart], active: usize, loader: &Loader) {
+        if !self.enabled || !self.playing {
+            return;
+        }
+        let period = 1.0 / STEPS[self.speed as usize];
+        self.elapsed += frame_time;
+        let steps = (self.elapsed / period) as usize;
+        self.elapsed -= steps as f32 * period;
+        for _ in 0..steps.min(MAX_STEPS) {
+            if !self.step(charts, active, loader) {
+                // ждем следующий кусок истории или сделки
+                let waiting = loader.is_loading(Target::More(active))
+                    || loader.is_loading(Target::Chart(active))
+                    || self.is_loading(loader, charts);
+                self.playing = waiting;
+                self.elapsed = 0.0;
+                break;
+            }
+        }
+    }
+
+    /// Requests the trades of the securities of the charts in the trade mode
+    pub fn load(&mut self, loader: &mut Loader, charts: &[Chart], range: &DateRange) {
+        if !self.enabled || self.mode == 0 {
+            return;
+        }
+        for chart in charts {
+            if !self.ticks.contains_key(&chart.security) {
+                // пустой список, чтобы не запрашивать повторно
+                self.ticks.insert(chart.security.clone(), vec![]);
+                loader.send(
+                    Target::Ticks(chart.security_active),
+                    Request::Ticks {
+                        security: chart.security.clone(),
+                        begin: range.begin,
+                        end: range.end - Duration::seconds(1),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Price of the last trade of the security made by the time, `None` outside
+    /// of the trade mode or until its trades are loaded
+    pub fn last_price(&self, security: &str) -> Option<f32> {
+        if !self.enabled || self.mode == 0 {
+            return None;
+        }
+        let ticks = self.ticks.get(security)?;
+        let i = ticks.partition_point(|a| a.time <= self.time);
+        i.checked_sub(1).map(|i| ticks[i].price)
+    }
+
+    pub fn add_ticks(&mut self, security: String, ticks: Vec<Tick>) {
+        self.ticks.insert(security, ticks);
+    }
+
+    /// Drops the trades of the previous range, they are loaded again for the new one
+    pub fn clear_ticks(&mut self, loader: &mut Loader, secs: &[&str]) {
+        for security in self.ticks.keys() {
+            if let Some(i) = secs.iter().position(|a| *a == security.as_str()) {
+                loader.cancel(Target::Ticks(i as i32));
+            }
+        }
+        self.ticks.clear();
+    }
+
+    pub fn is_loading(&self, loader: &Loader, charts: &[Chart]) -> bool {
+        charts
+            .iter()
+            .any(|a| loader.is_loading(Target::Ticks(a.security_active)))
+    }
+
+    /// Hides the future on the charts, shows the whole history again after the replay
+    pub fn apply(&mut self, charts: &mut [Chart], cells: &[Cell]) {
+        if !self.enabled && !self.applied {
+            return;
+        }
+        self.applied = self.enabled;
+        let time = self.time();
+        for (chart, cell) in charts.iter_mut().zip(cells.iter()) {
+            let ticks = self.ticks.get(&chart.security).map_or(&[][..], |a| {
+                &a[..a.partition_point(|tick| tick.time <= self.time)]
+            });
+            chart.viewport.set_replay(&cell.chart, time, ticks);
+            chart.update_view(cell);
+        }
+    }
+
+    /// Operations made by the time of the replay
+    pub fn operations<'a>(&self, operations: &'a [Operation]) -> Cow<'a, [Operation]> {
+        match self.time() {
+            Some(time) => Cow::Owned(
+                operations
+                    .iter()
+                    .filter(|a| a.time_at <= time)
+                    .cloned()
+                    .collect(),
+            ),
+            None => Cow::Borrowed(operations),
+        }
+    }
+
+    /// Replay toggle, mode, play/pause, step and speed with the time under them,
+    /// returns the command of a pressed button
+    pub fn draw(
+        &mut self,
+        d: &mut RaylibDrawHandle,
+        font: &Font,
+        position: Vector2,
+        loading: bool,
+    ) -> Option<Command> {
+        let mut command = None;
+        let mut enabled = self.enabled;
+        d.gui_toggle(
+            Rectangle::new(position.x, position.y, 75.0, 30.0),
+            "REPLAY",
+            &mut enabled,
+        );
+        if enabled != self.enabled {
+            command = Some(Command::Replay);
+        }
+        if !self.enabled {
+            return command;
+        }
+
+        let bounds = Rectangle::new(position.x + 80.0, position.y, 65.0, 30.0);
+        d.gui_toggle_group(bounds, MODES, &mut self.mode);
+
+        let y = position.y + 35.0;
+        let label = if self.playing { "PAUSE" } else { "PLAY" };
+        if d.gui_button(Rectangle::new(position.x, y, 50.0, 30.0), label) {
+            command = Some(Command::ReplayPlay);
+        }
+        if d.gui_button(Rectangle::new(position.x + 55.0, y, 50.0, 30.0), "STEP") {
+            command = Some(Command::ReplayStep);
+        }
+        let bounds = Rectangle::new(position.x + 110.0, y, 20.0, 30.0);
+        d.gui_toggle_group(bounds, SPEEDS, &mut self.speed);
+
+        let text = if loading {
+            String::from("LOADING...")
+        } else {
+            let speed = SPEEDS.split(";").nth(self.speed as usize).unwrap_or("1");
+            format!("{}  {}/S", self.time.format(DATE_TIME_FMT), speed)
+        };
+        let position = Vector2::new(position.x, position.y + 72.0);
+        d.draw_text_ex(font, &text, position, 15.0, 0.0, Color::WHEAT);
+
+        command
+    }
+}
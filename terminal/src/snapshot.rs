@@ -0,0 +1,101 @@
+use crate::chart::{Chart, FRAMES};
+use crate::layout::Cell;
+use crate::overlays::{Overlays, overlay_lines};
+use app::chart::{ChartLayers, ChartLine, save_svg};
+use app::models::common::{Drawing, Operation};
+use chrono::Local;
+use raylib::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SVG_SIZE: (u32, u32) = (1280, 800);
+
+/// Pixels of the cell as they are drawn in the current frame, to PNG
+pub fn save_png(
+    d: &mut RaylibDrawHandle,
+    thread: &RaylibThread,
+    cell: &Cell,
+    chart: &Chart,
+) -> Result<PathBuf, String> {
+    let path = snapshot_path(chart, "png")?;
+    // отрисованное за кадр еще в батче, без этого его не будет на снимке
+    unsafe { ffi::rlDrawRenderBatchActive() };
+    let mut image = d.load_image_from_screen(thread);
+    let scale = image.width() as f32 / d.get_screen_width() as f32;
+    let bounds = cell.bounds();
+    image.crop(Rectangle::new(
+        bounds.x * scale,
+        bounds.y * scale,
+        bounds.width * scale,
+        bounds.height * scale,
+    ));
+    image.export_image(path.to_str().expect("failed to convert snapshot path"));
+    Ok(path)
+}
+
+/// Visible candles of the chart with trades, indicators, operations and drawings, to SVG
+pub fn export_svg(
+    chart: &Chart,
+    overlays: &Overlays,
+    operations: &[Operation],
+    drawings: &[Drawing],
+) -> Result<PathBuf, String> {
+    let path = snapshot_path(chart, "svg")?;
+    let (first, last) = match (chart.candles.first(), chart.candles.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err(String::from("Has no candles to export")),
+    };
+    let title = format!(
+        "{} {} {} - {}",
+        chart.security,
+        frame_name(chart),
+        first.begin,
+        last.end
+    );
+
+    let operations = operations
+        .iter()
+        .filter(|a| {
+            a.security == chart.security && a.time_at >= first.begin && a.time_at <= last.end
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let lines = overlay_lines(&chart.indicators, overlays)
+        .into_iter()
+        .map(|(values, color)| ChartLine {
+            values,
+            color: (color.r, color.g, color.b),
+        })
+        .collect();
+    let layers = ChartLayers {
+        trades: &chart.trades,
+        lines,
+        operations: &operations,
+        drawings,
+    };
+    save_svg(&path, &title, &chart.candles, &layers, SVG_SIZE)
+        .map_err(|e| format!("{e}, failed to export {}", path.display()))?;
+    Ok(path)
+}
+
+/// New file in the directory of the security in GRAPHS_DIR, like `app::draw_candles`
+fn snapshot_path(chart: &Chart, extension: &str) -> Result<PathBuf, String> {
+    let dir = dotenv::var("GRAPHS_DIR").map_err(|_| String::from("GRAPHS_DIR is not set"))?;
+    let dir = Path::new(&dir).join(&chart.security);
+    fs::create_dir_all(&dir).map_err(|e| format!("{e}, failed to create {}", dir.display()))?;
+    let file_name = format!(
+        "{}_{}_{}.{}",
+        chart.security,
+        frame_name(chart),
+        Local::now().format("%Y%m%d_%H%M%S"),
+        extension
+    );
+    Ok(dir.join(file_name))
+}
+
+fn frame_name(chart: &Chart) -> &'static str {
+    FRAMES
+        .split(";")
+        .nth(chart.frame_active as usize)
+        .unwrap_or("m1")
+}
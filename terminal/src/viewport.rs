@@ -0,0 +1,416 @@
+use crate::{CANDLE_W, DrawCoords};
+use app::db::pg;
+use app::models::common::{Candle, Frame, Tick, TradeView};
+use chrono::{Duration, NaiveDateTime};
+use raylib::prelude::*;
+use sqlx::PgPool;
+use std::ops::Range;
+
+const MIN_CANDLE_W: f32 = 4.0;
+const MAX_CANDLE_W: f32 = 40.0;
+/// Change of the candle width per mouse wheel step
+const ZOOM_STEP: f32 = 0.1;
+/// Candles scrolled per frame while an arrow key is held, x10 with shift
+const SCROLL_SPEED: f32 = 0.5;
+/// Empty chunks in a row after which a side is considered fully loaded
+const MAX_EMPTY_CHUNKS: u32 = 10;
+const CHUNK_LIMIT: i32 = 100_000;
+
+/// Side of the history a chunk is loaded to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Part of the history shown during the replay
+struct Shown {
+    /// candles that ended by the time of the replay
+    count: usize,
+    /// candle that contains the time, built from the trades so far
+    forming: Option<(Candle, TradeView)>,
+}
+
+/// Loaded candles and trades of one security and frame with the visible window over them
+pub struct Viewport {
+    /// sorted by time, extended on both sides while scrolling
+    pub history: Vec<Candle>,
+    /// trades of the period of every candle of `history`
+    pub trades: Vec<TradeView>,
+    /// index of the first visible candle, fractional while dragging
+    pub offset: f32,
+    pub candle_w: f32,
+    /// loaded range, `end` is exclusive
+    begin: NaiveDateTime,
+    end: NaiveDateTime,
+    empty_left: u32,
+    empty_right: u32,
+    drag: bool,
+    /// `None` outside of the replay
+    shown: Option<Shown>,
+}
+
+impl Viewport {
+    /// Candles of the range, more are loaded around it while scrolling,
+    /// `None` if there are none. Runs in the task of the loader, the render
+    /// loop never awaits it
+    pub async fn load(
+        pool: &PgPool,
+        security: &str,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+        frame: &Frame,
+    ) -> Option<Self> {
+        let (history, trades) = load_chunk(pool, security, begin, end, frame).await;
+        if history.is_empty() {
+            return None;
+        }
+        Some(Self {
+            history,
+            trades,
+            offset: 0.0,
+            candle_w: CANDLE_W,
+            begin,
+            end,
+            empty_left: 0,
+            empty_right: 0,
+            drag: false,
+            shown: None,
+        })
+    }
+
+    /// Number of candles that fit into the chart
+    pub fn count(&self, chart: &Rectangle) -> usize {
+        ((chart.width / self.candle_w) as usize).saturating_sub(1)
+    }
+
+    /// Number of candles that can be shown, less than loaded during the replay
+    fn shown_len(&self) -> usize {
+        match &self.shown {
+            Some(shown) => shown.count + shown.forming.is_some() as usize,
+            None => self.history.len(),
+        }
+    }
+
+    fn max_offset(&self, chart: &Rectangle) -> f32 {
+        self.shown_len().saturating_sub(self.count(chart)) as f32
+    }
+
+    /// Hides the candles after `time` of the replay, the candle that contains it
+    /// is built from the `ticks` made by that time, `None` shows the whole history.
+    /// The last candle stays in view if it was
+    pub fn set_replay(&mut self, chart: &Rectangle, time: Option<NaiveDateTime>, ticks: &[Tick]) {
+        let Some(time) = time else {
+            self.shown = None;
+            return;
+        };
+        let follow = self.shown.is_none() || self.offset + 1.0 >= self.max_offset(chart);
+        let count = self.history.partition_point(|a| a.end <= time);
+        let forming = self
+            .history
+            .get(count)
+            .filter(|a| a.begin <= time)
+            .and_then(|a| forming(a, ticks));
+        self.shown = Some(Shown { count, forming });
+        if follow || self.offset > self.max_offset(chart) {
+            self.offset = self.max_offset(chart);
+        }
+    }
+
+    /// Last candle made by the time of the replay, the last loaded one outside of it
+    pub fn last_shown(&self) -> Option<&Candle> {
+        match &self.shown {
+            Some(shown) => match &shown.forming {
+                Some((candle, _)) => Some(candle),
+                None => shown.count.checked_sub(1).map(|i| &self.history[i]),
+            },
+            None => self.history.last(),
+        }
+    }
+
+    /// Indexes in `history` of the visible candles, without the forming one of the replay
+    pub fn visible(&self, chart: &Rectangle) -> Range<usize> {
+        let (first, _, complete) = self.window(chart);
+        first.min(complete)..complete
+    }
+
+    /// First and last visible candle and the end of the complete ones
+    fn window(&self, chart: &Rectangle) -> (usize, usize, usize) {
+        let len = self.shown_len();
+        let first = (self.offset as usize).min(len);
+        let last = (first + self.count(chart)).min(len);
+        let complete = last.min(self.shown.as_ref().map_or(len, |a| a.count));
+        (first, last, complete)
+    }
+
+    /// Visible candles with their trades and coords scaled to their prices
+    pub fn view(&self, chart: &Rectangle) -> (Vec<Candle>, Vec<TradeView>, DrawCoords) {
+        let (_, last, complete) = self.window(chart);
+        let range = self.visible(chart);
+        let mut candles = self.history[range.clone()].to_vec();
+        let mut trades = self.trades[range].to_vec();
+        if last > complete
+            && let Some((candle, trade)) = self.shown.as_ref().and_then(|a| a.forming.as_ref())
+        {
+            candles.push(candle.clone());
+            trades.push(trade.clone());
+        }
+
+        let start_pos = Vector2::new(chart.x, chart.y);
+        let end_pos = Vector2::new(chart.x + chart.width, chart.y + chart.height);
+        let mut min_low = f32::MAX;
+        let mut max_high = 0_f32;
+        for candle in candles.iter() {
+            min_low = f32::min(min_low, candle.low);
+            max_high = f32::max(max_high, candle.high);
+        }
+        // в начале replay свечей может не быть
+        let min_y = if candles.is_empty() {
+            0.0
+        } else {
+            f32::floor(min_low)
+        };
+        let max_y = f32::max(f32::ceil(max_high), min_y + 1.0);
+        let coords = DrawCoords {
+            start_pos,
+            end_pos,
+            step_y: (end_pos.y - start_pos.y) / (max_y - min_y),
+            min_y,
+            max_y,
+            candle_w: self.candle_w,
+        };
+
+        (candles, trades, coords)
+    }
+
+    /// X of the center of the candle that contains `time`, may be outside
+    /// of the chart, `None` if that candle is not loaded
+    pub fn time_x(&self, chart: &Rectangle, time: NaiveDateTime) -> Option<f32> {
+        let i = self
+            .history
+            .partition_point(|a| a.begin <= time)
+            .checked_sub(1)?;
+        let first = self.offset as usize;
+        Some(chart.x + (i as f32 - first as f32) * self.candle_w + self.candle_w * 1.5)
+    }
+
+    /// Begin of the candle under `x`, same as under the crosshair
+    pub fn x_time(&self, chart: &Rectangle, x: f32) -> Option<NaiveDateTime> {
+        let i = ((x - chart.x) / self.candle_w).floor() as i64 - 1;
+        let i = usize::try_from(self.offset as i64 + i).ok()?;
+        (i < self.shown_len()).then(|| self.history[i].begin)
+    }
+
+    /// Zoom with the mouse wheel around the cursor, pan by dragging and with
+    /// the left/right arrows if `keys`, returns true when the visible window changed
+    pub fn handle_input(&mut self, d: &RaylibDrawHandle, chart: &Rectangle, keys: bool) -> bool {
+        let mouse = d.get_mouse_position();
+        let inside = chart.check_collision_point_rec(mouse);
+        let before = (self.offset, self.candle_w);
+
+        let wheel = d.get_mouse_wheel_move();
+        if inside && wheel != 0.0 {
+            // свеча под курсором остается на месте
+            let cursor = (mouse.x - chart.x) / self.candle_w;
+            let anchor = self.offset + cursor;
+            self.candle_w =
+                (self.candle_w * (1.0 + ZOOM_STEP * wheel)).clamp(MIN_CANDLE_W, MAX_CANDLE_W);
+            self.offset = anchor - (mouse.x - chart.x) / self.candle_w;
+        }
+
+        if inside && d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.drag = true;
+        }
+        if !d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.drag = false;
+        }
+        if self.drag {
+            self.offset -= d.get_mouse_delta().x / self.candle_w;
+        }
+
+        let speed = if d.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) {
+            SCROLL_SPEED * 10.0
+        } else {
+            SCROLL_SPEED
+        };
+        if keys && d.is_key_down(KeyboardKey::KEY_LEFT) {
+            self.offset -= speed;
+        }
+        if keys && d.is_key_down(KeyboardKey::KEY_RIGHT) {
+            self.offset += speed;
+        }
+
+        self.offset = self.offset.clamp(0.0, self.max_offset(chart));
+
+        before != (self.offset, self.candle_w)
+    }
+
+    /// Range of the next chunk to load on a side with less than a screen
+    /// of candles left, requested through the loader and added by `add_chunk`
+    /// when it comes, so scrolling never waits for Postgres
+    pub fn next_chunk(
+        &self,
+        frame: &Frame,
+        chart: &Rectangle,
+    ) -> Option<(Side, NaiveDateTime, NaiveDateTime)> {
+        let count = self.count(chart) as f32;
+        if self.offset < count && self.empty_left < MAX_EMPTY_CHUNKS {
+            return Some((Side::Left, self.begin - chunk(frame), self.begin));
+        }
+        if self.offset + 2.0 * count > self.history.len() as f32
+            && self.empty_right < MAX_EMPTY_CHUNKS
+        {
+            return Some((Side::Right, self.end, self.end + chunk(frame)));
+        }
+        None
+    }
+
+    /// Adds a chunk from `next_chunk`, a chunk that doesn't border the loaded range
+    /// is ignored, returns true when the history changed
+    pub fn add_chunk(
+        &mut self,
+        side: Side,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+        candles: Vec<Candle>,
+        trades: Vec<TradeView>,
+    ) -> bool {
+        match side {
+            Side::Left if end == self.begin => {
+                self.begin = begin;
+                if candles.is_empty() {
+                    self.empty_left += 1;
+                    return false;
+                }
+                self.empty_left = 0;
+                self.offset += candles.len() as f32;
+                if let Some(shown) = &mut self.shown {
+                    shown.count += candles.len();
+                }
+                self.history.splice(0..0, candles);
+                self.trades.splice(0..0, trades);
+                true
+            }
+            Side::Right if begin == self.end => {
+                self.end = end;
+                if candles.is_empty() {
+                    self.empty_right += 1;
+                    return false;
+                }
+                self.empty_right = 0;
+                self.history.extend(candles);
+                self.trades.extend(trades);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Candle and trades of the part of `candle` made by the `ticks`,
+/// `None` if there are no ticks in it yet
+fn forming(candle: &Candle, ticks: &[Tick]) -> Option<(Candle, TradeView)> {
+    let from = ticks.partition_point(|a| a.time < candle.begin);
+    let to = ticks.partition_point(|a| a.time <= candle.end);
+    let ticks = &ticks[from..to];
+    let (first, last) = (ticks.first()?, ticks.last()?);
+
+    let mut forming = Candle {
+        open: first.price,
+        close: last.price,
+        high: first.price,
+        low: first.price,
+        value: 0.0,
+        volume: 0.0,
+        begin: candle.begin,
+        end: candle.end,
+        position_x: None,
+        position_y: None,
+    };
+    let mut trade = TradeView {
+        trade_period: candle.begin,
+        ..Default::default()
+    };
+    for tick in ticks {
+        let value = tick.price * tick.quantity as f32;
+        forming.high = forming.high.max(tick.price);
+        forming.low = forming.low.min(tick.price);
+        forming.value += value;
+        forming.volume += tick.quantity as f32;
+
+        trade.buysell.push_str(&tick.buysell);
+        trade.quantity_all += tick.quantity as i64;
+        trade.value_all += value;
+        if tick.buysell == "B" {
+            trade.quantity_buy += tick.quantity as i64;
+            trade.value_buy += value;
+        } else {
+            trade.quantity_sell += tick.quantity as i64;
+            trade.value_sell += value;
+        }
+    }
+    // средние цены взвешены по объему
+    let average = |value: f32, quantity: i64| {
+        if quantity > 0 {
+            value / quantity as f32
+        } else {
+            0.0
+        }
+    };
+    trade.price_all = average(trade.value_all, trade.quantity_all);
+    trade.price_buy = average(trade.value_buy, trade.quantity_buy);
+    trade.price_sell = average(trade.value_sell, trade.quantity_sell);
+    Some((forming, trade))
+}
+
+/// Days loaded at once, whole days because the aggregated frames are filtered by date
+fn chunk(frame: &Frame) -> Duration {
+    match frame {
+        Frame::M1 => Duration::days(1),
+        Frame::M15 => Duration::days(5),
+        Frame::H1 => Duration::days(20),
+        Frame::D1 => Duration::days(365),
+    }
+}
+
+/// Candles and trades of the range, loaded together so they always match
+pub async fn load_chunk(
+    pool: &PgPool,
+    security: &str,
+    begin: NaiveDateTime,
+    end: NaiveDateTime,
+    frame: &Frame,
+) -> (Vec<Candle>, Vec<TradeView>) {
+    let end = end - Duration::seconds(1);
+    let candles = pg::get_candles(pool, security, begin, end, CHUNK_LIMIT, frame).await;
+    if candles.is_empty() {
+        return (candles, vec![]);
+    }
+    let trades = pg::get_trades_view(pool, security, begin, end, frame, CHUNK_LIMIT).await;
+    let trades = align(&candles, &trades, frame);
+    (candles, trades)
+}
+
+/// Trades of the period that contains the begin of every candle,
+/// empty ones if there were none
+fn align(candles: &[Candle], trades: &[TradeView], frame: &Frame) -> Vec<TradeView> {
+    let period = match frame {
+        Frame::M1 => Duration::minutes(1),
+        Frame::M15 => Duration::minutes(15),
+        Frame::H1 => Duration::hours(1),
+        Frame::D1 => Duration::days(1),
+    };
+    candles
+        .iter()
+        .map(|candle| {
+            let i = trades.partition_point(|a| a.trade_period <= candle.begin);
+            match i.checked_sub(1).map(|i| &trades[i]) {
+                Some(trade) if candle.begin < trade.trade_period + period => trade.clone(),
+                _ => TradeView {
+                    trade_period: candle.begin,
+                    ..Default::default()
+                },
+            }
+        })
+        .collect()
+}